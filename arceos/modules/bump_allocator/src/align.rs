@@ -0,0 +1,78 @@
+//! Address alignment helpers.
+//!
+//! Every `align` argument is expected to be a power of two. The `_checked`
+//! variants verify that and report overflows instead of wrapping around.
+
+/// Rounds `addr` up to a multiple of `align`.
+///
+/// The result is meaningless if it overflows; use [`align_up_checked`] for
+/// addresses near `usize::MAX`.
+///
+/// ```
+/// use bump_allocator::align::align_up;
+///
+/// assert_eq!(align_up(0x1000, 0x1000), 0x1000);
+/// assert_eq!(align_up(0x1001, 0x1000), 0x2000);
+/// assert_eq!(align_up(0, 8), 0);
+/// assert_eq!(align_up(13, 1), 13);
+/// ```
+#[inline]
+pub const fn align_up(addr: usize, align: usize) -> usize {
+    (addr + align - 1) & !(align - 1)
+}
+
+/// Rounds `addr` down to a multiple of `align`.
+///
+/// ```
+/// use bump_allocator::align::align_down;
+///
+/// assert_eq!(align_down(0x1fff, 0x1000), 0x1000);
+/// assert_eq!(align_down(0x2000, 0x1000), 0x2000);
+/// assert_eq!(align_down(usize::MAX, 0x1000), usize::MAX - 0xfff);
+/// ```
+#[inline]
+pub const fn align_down(addr: usize, align: usize) -> usize {
+    addr & !(align - 1)
+}
+
+/// Rounds `addr` up to a multiple of `align`.
+///
+/// Returns `None` if `align` is not a power of two or the result overflows.
+///
+/// ```
+/// use bump_allocator::align::align_up_checked;
+///
+/// assert_eq!(align_up_checked(0x1001, 0x1000), Some(0x2000));
+/// assert_eq!(align_up_checked(usize::MAX - 0xfff, 0x1000), Some(usize::MAX - 0xfff));
+/// assert_eq!(align_up_checked(usize::MAX - 0xffe, 0x1000), None);
+/// assert_eq!(align_up_checked(0x1000, 3), None);
+/// ```
+#[inline]
+pub const fn align_up_checked(addr: usize, align: usize) -> Option<usize> {
+    if !align.is_power_of_two() {
+        return None;
+    }
+    match addr.checked_add(align - 1) {
+        Some(addr) => Some(addr & !(align - 1)),
+        None => None,
+    }
+}
+
+/// Rounds `addr` down to a multiple of `align`.
+///
+/// Returns `None` if `align` is not a power of two.
+///
+/// ```
+/// use bump_allocator::align::align_down_checked;
+///
+/// assert_eq!(align_down_checked(0x1fff, 0x1000), Some(0x1000));
+/// assert_eq!(align_down_checked(0x1fff, 0), None);
+/// ```
+#[inline]
+pub const fn align_down_checked(addr: usize, align: usize) -> Option<usize> {
+    if align.is_power_of_two() {
+        Some(align_down(addr, align))
+    } else {
+        None
+    }
+}
@@ -1,6 +1,21 @@
-#![no_std]
+#![cfg_attr(not(test), no_std)]
 
-use allocator::{BaseAllocator, ByteAllocator, PageAllocator};
+use allocator::{AllocError, AllocResult, BaseAllocator, ByteAllocator, PageAllocator};
+use core::alloc::Layout;
+use core::fmt;
+use core::ptr::NonNull;
+#[cfg(feature = "atomic-stats")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+pub mod align;
+mod locked;
+
+#[cfg(test)]
+mod tests;
+
+pub use self::locked::LockedEarlyAllocator;
+
+use self::align::{align_down, align_up_checked};
 
 /// Early memory allocator
 /// Use it before formal bytes-allocator and pages-allocator can work!
@@ -14,18 +29,1253 @@ use allocator::{BaseAllocator, ByteAllocator, PageAllocator};
 ///
 /// For bytes area, 'count' records number of allocations.
 /// When it goes down to ZERO, free bytes-used area.
-/// For pages area, it will never be freed!
+/// For pages area, it will never be freed! `used_pages` counts the pages
+/// currently handed out, while `page_count` records number of allocations.
+///
+/// [`EarlyAllocator::alloc_pages_front`] takes pages from `b_pos` instead.
+/// Since they are never freed either, freeing the bytes area only moves
+/// `b_pos` back to the end of the last such block.
 ///
-pub struct EarlyAllocator;
+/// With [`EarlyAllocator::set_free_list`], freed blocks are also kept in
+/// per-size free lists and handed out again to allocations of the same size,
+/// before the whole bytes area can be freed.
+///
+/// Memory added by [`BaseAllocator::add_memory`] that is not contiguous with
+/// `end` is kept as an extra double-ended region, only used once the primary
+/// region is exhausted. `MAX_REGIONS` bounds the number of regions, the
+/// primary one included, so it must be at least 1. The default of 1 allows no
+/// extra region and takes no space for one.
+///
+pub struct EarlyAllocator<const PAGE_SIZE: usize, const MAX_REGIONS: usize = 1> {
+    /// The primary region, then the extra ones.
+    regions: [Region; MAX_REGIONS],
+    regions_len: usize,
+    byte_count: usize,
+    page_count: usize,
+    requested_bytes: usize,
+    pages_used: usize,
+    /// Bytes taken by [`EarlyAllocator::alloc_pages_front`], in all regions.
+    front_page_bytes: usize,
+    reserved: Reservations,
+    free: FreeLists,
+    peak_bytes_used: usize,
+    peak_pages_used: usize,
+    low_memory_threshold: usize,
+    low_memory_callback: Option<fn()>,
+    low_memory_armed: bool,
+    /// Set by [`EarlyAllocator::seal`].
+    sealed: bool,
+    #[cfg(feature = "atomic-stats")]
+    mirror: AtomicStats,
+    #[cfg(feature = "page-blocks")]
+    page_blocks: PageBlocks,
+}
+
+/// Maximum number of spans that can be carved out by
+/// [`EarlyAllocator::reserve`].
+pub const MAX_RESERVED: usize = 4;
+
+/// Pattern written over the bytes area when it is freed, in debug builds.
+pub const POISON_BYTE: u8 = 0xde;
+
+/// Maximum number of page blocks recorded with the `page-blocks` feature,
+/// see [`EarlyAllocator::allocated_pages`].
+#[cfg(feature = "page-blocks")]
+pub const MAX_PAGE_BLOCKS: usize = 16;
+
+/// Maximum number of distinct block sizes kept by the free lists, see
+/// [`EarlyAllocator::set_free_list`].
+pub const FREE_LIST_SIZES: usize = 8;
+
+/// A double-ended memory region, laid out as described on [`EarlyAllocator`].
+#[derive(Clone, Copy)]
+struct Region {
+    start: usize,
+    end: usize,
+    byte_next: usize,
+    page_next: usize,
+    /// Where `byte_next` returns to when the bytes area is freed.
+    byte_floor: usize,
+}
+
+impl Region {
+    const EMPTY: Self = Self::new(0, 0);
+
+    const fn new(start: usize, end: usize) -> Self {
+        Self {
+            start,
+            end,
+            byte_next: start,
+            page_next: end,
+            byte_floor: start,
+        }
+    }
+
+    fn overlaps(&self, start: usize, end: usize) -> bool {
+        start < self.end && self.start < end
+    }
+}
+
+/// Spans excluded from allocation, see [`EarlyAllocator::reserve`].
+#[derive(Clone, Copy)]
+struct Reservations {
+    spans: [(usize, usize); MAX_RESERVED],
+    len: usize,
+}
+
+impl Reservations {
+    const fn new() -> Self {
+        Self {
+            spans: [(0, 0); MAX_RESERVED],
+            len: 0,
+        }
+    }
+
+    fn push(&mut self, start: usize, end: usize) -> AllocResult {
+        if self.len == MAX_RESERVED {
+            return Err(AllocError::NoMemory);
+        }
+        self.spans[self.len] = (start, end);
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Returns the reserved span intersecting `[start, end)`, if any.
+    fn find_overlap(&self, start: usize, end: usize) -> Option<(usize, usize)> {
+        self.spans[..self.len]
+            .iter()
+            .copied()
+            .find(|&(s, e)| start < e && s < end)
+    }
+
+    /// Returns the pieces of `[start, end)` not covered by a reserved span,
+    /// in address order. Some may be empty.
+    fn gaps(&self, start: usize, end: usize) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let mut cursor = Some(start);
+        core::iter::from_fn(move || {
+            let from = cursor?;
+            let next = self.spans[..self.len]
+                .iter()
+                .copied()
+                .filter(|&(s, e)| from < e && s < end)
+                .min_by_key(|&(s, _)| s);
+            cursor = next.map(|(_, e)| e);
+            let to = next.map_or(end, |(s, _)| s);
+            Some((from, to.max(from)))
+        })
+    }
+
+    /// Returns the largest piece of `[start, end)` not covered by a
+    /// reserved span.
+    fn largest_gap(&self, start: usize, end: usize) -> usize {
+        self.gaps(start, end).map(|(s, e)| e - s).max().unwrap_or(0)
+    }
+
+    /// Returns how many bytes of `[start, end)` are reserved.
+    fn overlap_len(&self, start: usize, end: usize) -> usize {
+        self.spans[..self.len]
+            .iter()
+            .map(|&(s, e)| e.min(end).saturating_sub(s.max(start)))
+            .sum()
+    }
+}
+
+/// The live page blocks, as `(start, num_pages)` in allocation order.
+#[cfg(feature = "page-blocks")]
+#[derive(Clone, Copy)]
+struct PageBlocks {
+    blocks: [(usize, usize); MAX_PAGE_BLOCKS],
+    len: usize,
+}
+
+#[cfg(feature = "page-blocks")]
+impl PageBlocks {
+    const fn new() -> Self {
+        Self {
+            blocks: [(0, 0); MAX_PAGE_BLOCKS],
+            len: 0,
+        }
+    }
+
+    /// Records a block, unless the table is full.
+    fn push(&mut self, start: usize, num_pages: usize) {
+        if self.len < MAX_PAGE_BLOCKS {
+            self.blocks[self.len] = (start, num_pages);
+            self.len += 1;
+        }
+    }
+
+    /// Forgets a block, if it was recorded.
+    fn remove(&mut self, start: usize, num_pages: usize) {
+        let blocks = &mut self.blocks[..self.len];
+        if let Some(i) = blocks.iter().position(|&b| b == (start, num_pages)) {
+            blocks.copy_within(i + 1.., i);
+            self.len -= 1;
+        }
+    }
+}
+
+/// A list of freed byte blocks of the same size.
+///
+/// It is intrusive: the first word of each block holds the address of the
+/// next one, and 0 ends the list.
+#[derive(Clone, Copy)]
+struct FreeList {
+    size: usize,
+    head: usize,
+}
+
+/// Free lists for up to [`FREE_LIST_SIZES`] block sizes.
+#[derive(Clone, Copy)]
+struct FreeLists {
+    lists: [FreeList; FREE_LIST_SIZES],
+    enabled: bool,
+}
+
+impl FreeLists {
+    const fn new() -> Self {
+        Self {
+            lists: [FreeList { size: 0, head: 0 }; FREE_LIST_SIZES],
+            enabled: false,
+        }
+    }
+
+    /// Takes a block fitting `layout` out of its list, if the first one is
+    /// aligned well enough.
+    fn pop(&mut self, layout: Layout) -> Option<usize> {
+        if !self.enabled {
+            return None;
+        }
+        let list = self
+            .lists
+            .iter_mut()
+            .find(|l| l.head != 0 && l.size == layout.size())?;
+        if list.head % layout.align() != 0 {
+            return None;
+        }
+        let block = list.head;
+        list.head = unsafe { *(block as *const usize) };
+        Some(block)
+    }
+
+    /// Puts the block of `size` bytes at `addr` on its list. Returns `false`
+    /// if it can't hold the link, or if every list is taken by other sizes.
+    fn push(&mut self, addr: usize, size: usize) -> bool {
+        let fits_link =
+            size >= core::mem::size_of::<usize>() && addr % core::mem::align_of::<usize>() == 0;
+        if !self.enabled || !fits_link {
+            return false;
+        }
+        let Some(list) = self
+            .lists
+            .iter()
+            .position(|l| l.head != 0 && l.size == size)
+            .or_else(|| self.lists.iter().position(|l| l.head == 0))
+        else {
+            return false;
+        };
+        let list = &mut self.lists[list];
+        unsafe { *(addr as *mut usize) = list.head };
+        list.size = size;
+        list.head = addr;
+        true
+    }
+
+    /// Empties every list, whose blocks are about to be reused by bumping.
+    fn clear(&mut self) {
+        for list in &mut self.lists {
+            list.head = 0;
+        }
+    }
+}
+
+/// The start address of a page block from [`EarlyAllocator::alloc_page_addr`].
+///
+/// Unlike a bare `usize`, it can't be mixed up with another kind of address:
+/// it only comes from the allocator, or from an explicit [`PageAddr::new`].
+///
+/// ```compile_fail
+/// use bump_allocator::EarlyAllocator;
+///
+/// let mut alloc = EarlyAllocator::<4096>::new();
+/// alloc.dealloc_page_addr(0x8000_0000usize, 1).unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct PageAddr(usize);
+
+impl PageAddr {
+    /// Wraps the start address of a page block.
+    pub const fn new(addr: usize) -> Self {
+        Self(addr)
+    }
+
+    /// Returns the address as a bare `usize`.
+    pub const fn as_usize(self) -> usize {
+        self.0
+    }
+}
+
+/// A saved position of the bytes area, see [`EarlyAllocator::checkpoint`].
+#[derive(Clone, Copy, Debug)]
+pub struct BumpMarker<const MAX_REGIONS: usize = 1> {
+    byte_next: [usize; MAX_REGIONS],
+    byte_count: usize,
+    requested_bytes: usize,
+}
+
+/// A snapshot of the [`EarlyAllocator`] usage, see [`EarlyAllocator::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EarlyAllocStats {
+    pub total_bytes: usize,
+    pub used_bytes: usize,
+    pub available_bytes: usize,
+    pub total_pages: usize,
+    pub used_pages: usize,
+    pub available_pages: usize,
+    /// Number of live byte allocations.
+    pub byte_count: usize,
+    /// Number of live page allocations.
+    pub page_count: usize,
+}
+
+/// [`EarlyAllocStats`] mirrored into atomics for lock-free observation.
+#[cfg(feature = "atomic-stats")]
+struct AtomicStats {
+    total_bytes: AtomicUsize,
+    used_bytes: AtomicUsize,
+    available_bytes: AtomicUsize,
+    total_pages: AtomicUsize,
+    used_pages: AtomicUsize,
+    available_pages: AtomicUsize,
+    byte_count: AtomicUsize,
+    page_count: AtomicUsize,
+}
+
+#[cfg(feature = "atomic-stats")]
+impl AtomicStats {
+    const fn new() -> Self {
+        Self {
+            total_bytes: AtomicUsize::new(0),
+            used_bytes: AtomicUsize::new(0),
+            available_bytes: AtomicUsize::new(0),
+            total_pages: AtomicUsize::new(0),
+            used_pages: AtomicUsize::new(0),
+            available_pages: AtomicUsize::new(0),
+            byte_count: AtomicUsize::new(0),
+            page_count: AtomicUsize::new(0),
+        }
+    }
+
+    fn store(&self, stats: EarlyAllocStats) {
+        self.total_bytes.store(stats.total_bytes, Ordering::Relaxed);
+        self.used_bytes.store(stats.used_bytes, Ordering::Relaxed);
+        self.available_bytes
+            .store(stats.available_bytes, Ordering::Relaxed);
+        self.total_pages.store(stats.total_pages, Ordering::Relaxed);
+        self.used_pages.store(stats.used_pages, Ordering::Relaxed);
+        self.available_pages
+            .store(stats.available_pages, Ordering::Relaxed);
+        self.byte_count.store(stats.byte_count, Ordering::Relaxed);
+        self.page_count.store(stats.page_count, Ordering::Relaxed);
+    }
+
+    fn load(&self) -> EarlyAllocStats {
+        EarlyAllocStats {
+            total_bytes: self.total_bytes.load(Ordering::Relaxed),
+            used_bytes: self.used_bytes.load(Ordering::Relaxed),
+            available_bytes: self.available_bytes.load(Ordering::Relaxed),
+            total_pages: self.total_pages.load(Ordering::Relaxed),
+            used_pages: self.used_pages.load(Ordering::Relaxed),
+            available_pages: self.available_pages.load(Ordering::Relaxed),
+            byte_count: self.byte_count.load(Ordering::Relaxed),
+            page_count: self.page_count.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> EarlyAllocator<PAGE_SIZE, MAX_REGIONS> {
+    const HAS_PRIMARY_REGION: () = assert!(MAX_REGIONS >= 1, "MAX_REGIONS is zero");
+
+    /// Creates an empty allocator. It must be initialized by
+    /// [`BaseAllocator::init`] before use.
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::HAS_PRIMARY_REGION;
+        Self {
+            regions: [Region::EMPTY; MAX_REGIONS],
+            regions_len: 1,
+            byte_count: 0,
+            page_count: 0,
+            requested_bytes: 0,
+            pages_used: 0,
+            front_page_bytes: 0,
+            reserved: Reservations::new(),
+            free: FreeLists::new(),
+            peak_bytes_used: 0,
+            peak_pages_used: 0,
+            low_memory_threshold: 0,
+            low_memory_callback: None,
+            low_memory_armed: true,
+            sealed: false,
+            #[cfg(feature = "atomic-stats")]
+            mirror: AtomicStats::new(),
+            #[cfg(feature = "page-blocks")]
+            page_blocks: PageBlocks::new(),
+        }
+    }
+
+    /// Creates an allocator managing `[start, start + size)`, without a
+    /// separate [`BaseAllocator::init`] call. Usable in `static` initializers.
+    pub const fn with_region(start: usize, size: usize) -> Self {
+        let mut alloc = Self::new();
+        alloc.regions[0] = Region::new(start, start + size);
+        alloc
+    }
+
+    /// Drops every allocation at once, keeping the memory regions.
+    ///
+    /// Both cursors return to the edges of their regions and the counters,
+    /// including the peak usage, are zeroed as if [`BaseAllocator::init`] had
+    /// just run.
+    pub fn reset(&mut self) {
+        self.byte_count = 0;
+        self.page_count = 0;
+        self.requested_bytes = 0;
+        self.pages_used = 0;
+        self.front_page_bytes = 0;
+        self.peak_bytes_used = 0;
+        self.peak_pages_used = 0;
+        self.free.clear();
+        #[cfg(feature = "page-blocks")]
+        {
+            self.page_blocks.len = 0;
+        }
+        for region in self.regions_mut() {
+            *region = Region::new(region.start, region.end);
+        }
+        self.after_change();
+    }
+
+    /// Returns all usage counters at once.
+    pub fn stats(&self) -> EarlyAllocStats {
+        EarlyAllocStats {
+            total_bytes: self.total_bytes(),
+            used_bytes: self.used_bytes(),
+            available_bytes: self.available_bytes(),
+            total_pages: self.total_pages(),
+            used_pages: self.used_pages(),
+            available_pages: self.available_pages(),
+            byte_count: self.byte_count,
+            page_count: self.page_count,
+        }
+    }
 
-impl EarlyAllocator {
+    /// Returns the sum of `layout.size()` over the live byte allocations.
+    ///
+    /// Unlike [`ByteAllocator::used_bytes`] it excludes alignment padding, so
+    /// `used_bytes() - requested_bytes()` is the internal fragmentation.
+    pub fn requested_bytes(&self) -> usize {
+        self.requested_bytes
+    }
+
+    /// Returns the highest [`ByteAllocator::used_bytes`] seen so far.
+    ///
+    /// It never decreases on `dealloc`, so it still reflects the worst-case
+    /// pressure after the bytes area has been freed.
+    pub fn peak_used_bytes(&self) -> usize {
+        self.peak_bytes_used
+    }
+
+    /// Returns the highest [`PageAllocator::used_pages`] seen so far.
+    pub fn peak_used_pages(&self) -> usize {
+        self.peak_pages_used
+    }
+
+    /// Returns the size of the largest contiguous free span, across all
+    /// regions.
+    ///
+    /// Without reservations this is the widest `[byte_next, page_next)` gap.
+    pub fn largest_free_block(&self) -> usize {
+        self.regions()
+            .iter()
+            .map(|r| self.reserved.largest_gap(r.byte_next, r.page_next))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the free gap `[b_pos, p_pos)` of the primary region, as
+    /// `(start, end)`, e.g. for a real allocator to take over after
+    /// [`EarlyAllocator::seal`].
+    ///
+    /// Reserved spans inside the gap are not left out.
+    pub fn free_region(&self) -> (usize, usize) {
+        (self.primary().byte_next, self.primary().page_next)
+    }
+
+    /// Stops handing out memory: from now on, every allocation, including
+    /// growing a block in place, fails with [`AllocError::NoMemory`].
+    ///
+    /// Existing blocks can still be freed. The seal outlives
+    /// [`EarlyAllocator::reset`], and only a new [`BaseAllocator::init`]
+    /// lifts it.
+    pub fn seal(&mut self) {
+        self.sealed = true;
+    }
+
+    /// Returns `true` if [`EarlyAllocator::seal`] has been called.
+    pub fn is_sealed(&self) -> bool {
+        self.sealed
+    }
+
+    /// Returns `true` if `ptr` lies in a used part of a region: below `b_pos`
+    /// or at or above `p_pos`, outside the spans carved out by
+    /// [`EarlyAllocator::reserve`].
+    ///
+    /// This is only a cheap range check, meant to catch foreign pointers.
+    /// Individual allocations are not tracked, so a pointer into the middle
+    /// of a block, or to a block already freed but not yet reclaimed, is
+    /// owned too.
+    pub fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let addr = ptr.as_ptr() as usize;
+        let used = self.regions().iter().any(|r| {
+            (r.start..r.byte_next).contains(&addr) || (r.page_next..r.end).contains(&addr)
+        });
+        used && self.reserved.find_overlap(addr, addr + 1).is_none()
+    }
+
+    /// Lends the free gap `[b_pos, p_pos)` of the primary region as a
+    /// scratch buffer, stopping short of the first reserved span in it.
+    ///
+    /// Nothing is allocated: the buffer only lives as long as the borrow,
+    /// which keeps any allocation from happening meanwhile, and later
+    /// allocations reuse the same memory.
+    pub fn scratch(&mut self) -> &mut [u8] {
+        let (byte_next, page_next) = self.free_region();
+        let end = self
+            .reserved
+            .find_overlap(byte_next, page_next)
+            .map_or(page_next, |(s, _)| s.max(byte_next));
+        let len = end - byte_next;
+        if len == 0 {
+            return &mut [];
+        }
+        // SAFETY: the gap is managed memory that nothing else refers to, and
+        // the mutable borrow of `self` keeps it that way.
+        unsafe { core::slice::from_raw_parts_mut(byte_next as *mut u8, len) }
+    }
+
+    /// Returns the percentage (0 to 100) of the managed bytes that are no
+    /// longer available, whether taken by bytes, pages or reservations.
+    pub fn utilization(&self) -> u8 {
+        let total = self.total_bytes();
+        if total == 0 {
+            return 0;
+        }
+        let unavailable = total - self.available_bytes();
+        (unavailable as u128 * 100 / total as u128) as u8
+    }
+
+    /// Saves the current position of the bytes area.
+    ///
+    /// Passing the marker to [`EarlyAllocator::rollback`] later frees every
+    /// byte allocation made in between, arena style.
+    pub fn checkpoint(&self) -> BumpMarker<MAX_REGIONS> {
+        let mut byte_next = [0; MAX_REGIONS];
+        for (next, region) in byte_next.iter_mut().zip(self.regions()) {
+            *next = region.byte_next;
+        }
+        BumpMarker {
+            byte_next,
+            byte_count: self.byte_count,
+            requested_bytes: self.requested_bytes,
+        }
+    }
+
+    /// Restores the bytes area to a position saved by
+    /// [`EarlyAllocator::checkpoint`].
+    ///
+    /// Fails with [`AllocError::InvalidParam`] if the marker lies ahead of
+    /// the current position, e.g. because the bytes area has been freed
+    /// since the marker was taken, or behind pages allocated by
+    /// [`EarlyAllocator::alloc_pages_front`] since.
+    pub fn rollback(&mut self, marker: BumpMarker<MAX_REGIONS>) -> AllocResult {
+        let invalid = marker.byte_count > self.byte_count
+            || self
+                .regions()
+                .iter()
+                .zip(marker.byte_next)
+                .any(|(r, next)| next < r.byte_floor || next > r.byte_next);
+        if invalid {
+            return Err(AllocError::InvalidParam);
+        }
+        self.free.clear();
+        self.byte_count = marker.byte_count;
+        self.requested_bytes = marker.requested_bytes;
+        for (region, next) in self.regions_mut().iter_mut().zip(marker.byte_next) {
+            region.byte_next = next;
+        }
+        self.after_change();
+        Ok(())
+    }
+
+    /// Carves `[start, start + size)` out of the free gap, so that no later
+    /// allocation overlaps it. Reservations survive [`EarlyAllocator::reset`].
+    ///
+    /// Fails with [`AllocError::InvalidParam`] if the span lies outside every
+    /// region, and with [`AllocError::MemoryOverlap`] if it intrudes on used
+    /// bytes, used pages or another reservation.
+    pub fn reserve(&mut self, start: usize, size: usize) -> AllocResult {
+        let end = match start.checked_add(size) {
+            Some(end) if size > 0 => end,
+            _ => return Err(AllocError::InvalidParam),
+        };
+        let region = self
+            .regions()
+            .iter()
+            .find(|r| r.start <= start && end <= r.end)
+            .ok_or(AllocError::InvalidParam)?;
+        if start < region.byte_next
+            || end > region.page_next
+            || self.reserved.find_overlap(start, end).is_some()
+        {
+            return Err(AllocError::MemoryOverlap);
+        }
+        self.reserved.push(start, end)?;
+        self.after_change();
+        Ok(())
+    }
+
+    /// Allocates `size` bytes aligned to `align`, like [`ByteAllocator::alloc`]
+    /// with the matching layout. Free the block with that layout too.
+    ///
+    /// Fails with [`AllocError::InvalidParam`] if `align` is not a power of
+    /// two.
+    pub fn alloc_aligned(&mut self, size: usize, align: usize) -> AllocResult<NonNull<u8>> {
+        if !align.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        let layout = Layout::from_size_align(size, align).map_err(|_| AllocError::InvalidParam)?;
+        self.alloc(layout)
+    }
+
+    /// Allocates bytes like [`ByteAllocator::alloc`], then fills the returned
+    /// `layout.size()` bytes with zeros.
+    pub fn alloc_zeroed(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        let ptr = self.alloc(layout)?;
+        unsafe { core::ptr::write_bytes(ptr.as_ptr(), 0, layout.size()) };
+        Ok(ptr)
+    }
+
+    /// Resizes the byte allocation at `ptr` to `new_layout`.
+    ///
+    /// If it is the most recent allocation of its region, it is grown or
+    /// shrunk in place by moving `b_pos`. Otherwise a new block is allocated,
+    /// the contents are copied over and the old block is freed. On failure
+    /// the old block is left untouched.
+    pub fn realloc(
+        &mut self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> AllocResult<NonNull<u8>> {
+        if let Some(ptr) = self.resize_in_place(ptr, old_layout, new_layout) {
+            return Ok(ptr);
+        }
+        let new_ptr = self.alloc(new_layout)?;
+        let len = old_layout.size().min(new_layout.size());
+        unsafe { core::ptr::copy_nonoverlapping(ptr.as_ptr(), new_ptr.as_ptr(), len) };
+        self.dealloc(ptr, old_layout);
+        Ok(new_ptr)
+    }
+
+    /// Gives back a byte allocation, like [`ByteAllocator::dealloc`].
+    ///
+    /// Fails with [`AllocError::NotAllocated`] if there are no outstanding
+    /// byte allocations, e.g. on a double free.
+    pub fn try_dealloc(&mut self, pos: NonNull<u8>, layout: Layout) -> AllocResult {
+        if layout.size() == 0 {
+            return Ok(());
+        }
+        if self.byte_count == 0 {
+            return Err(AllocError::NotAllocated);
+        }
+        self.byte_count -= 1;
+        self.requested_bytes = self.requested_bytes.saturating_sub(layout.size());
+        if self.byte_count == 0 {
+            #[cfg(debug_assertions)]
+            self.poison_bytes();
+            self.free.clear();
+            for region in self.regions_mut() {
+                region.byte_next = region.byte_floor;
+            }
+        } else {
+            self.free.push(pos.as_ptr() as usize, layout.size());
+        }
+        self.after_change();
+        Ok(())
+    }
+
+    /// Allocates pages like [`PageAllocator::alloc_pages`], but forward from
+    /// `b_pos` rather than backward from `p_pos`.
+    ///
+    /// The pages count as used pages, not used bytes. Like all pages, they
+    /// are never freed, so the bytes area can no longer shrink below them.
+    pub fn alloc_pages_front(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        if self.sealed {
+            return Err(AllocError::NoMemory);
+        }
+        let size = PAGE_SIZE
+            .checked_mul(num_pages)
+            .ok_or(AllocError::NoMemory)?;
+        let layout =
+            Layout::from_size_align(size, align_pow2).map_err(|_| AllocError::InvalidParam)?;
+        let reserved = &self.reserved;
+        let region = self.regions[..self.regions_len].iter_mut().find_map(|r| {
+            bump_bytes(r.byte_next, r.page_next, layout, reserved).map(|pos| (r, pos))
+        });
+        let (region, (start, end)) = region.ok_or(AllocError::NoMemory)?;
+        region.byte_next = end;
+        region.byte_floor = end;
+        self.page_count += 1;
+        self.pages_used += num_pages;
+        self.front_page_bytes += size;
+        self.peak_pages_used = self.peak_pages_used.max(self.used_pages());
+        self.record_page_block(start, num_pages);
+        self.after_change();
+        Ok(start)
+    }
+
+    /// Allocates pages like [`PageAllocator::alloc_pages`], returning the
+    /// block as a slice pointer spanning `num_pages * PAGE_SIZE` bytes.
+    pub fn alloc_pages_ptr(
+        &mut self,
+        num_pages: usize,
+        align_pow2: usize,
+    ) -> AllocResult<NonNull<[u8]>> {
+        let start = self.alloc_pages(num_pages, align_pow2)?;
+        let ptr = NonNull::new(start as *mut u8).ok_or(AllocError::NoMemory)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, num_pages * PAGE_SIZE))
+    }
+
+    /// Allocates pages like [`PageAllocator::alloc_pages`], returning their
+    /// start as a [`PageAddr`].
+    pub fn alloc_page_addr(
+        &mut self,
+        num_pages: usize,
+        align_pow2: usize,
+    ) -> AllocResult<PageAddr> {
+        self.alloc_pages(num_pages, align_pow2).map(PageAddr)
+    }
+
+    /// Gives back pages from [`EarlyAllocator::alloc_page_addr`], like
+    /// [`EarlyAllocator::try_dealloc_pages`].
+    pub fn dealloc_page_addr(&mut self, pos: PageAddr, num_pages: usize) -> AllocResult {
+        self.try_dealloc_pages(pos.as_usize(), num_pages)
+    }
+
+    /// Gives back a page allocation, like [`PageAllocator::dealloc_pages`].
+    ///
+    /// Fails with [`AllocError::NotAllocated`] if there are no outstanding
+    /// page allocations.
+    pub fn try_dealloc_pages(&mut self, pos: usize, num_pages: usize) -> AllocResult {
+        if self.page_count == 0 {
+            return Err(AllocError::NotAllocated);
+        }
+        self.page_count -= 1;
+        self.pages_used = self.pages_used.saturating_sub(num_pages);
+        self.forget_page_block(pos, num_pages);
+        self.after_change();
+        Ok(())
+    }
+
+    /// Extends the most recent page allocation of a region, starting at
+    /// `current_start` and spanning `current_pages`, by `extra_pages` more
+    /// pages below it, returning the new start.
+    ///
+    /// The page area grows backward, so this just moves `p_pos` further
+    /// down: the old pages keep their contents and address, which is now
+    /// `extra_pages * PAGE_SIZE` bytes into the block.
+    ///
+    /// Fails with [`AllocError::InvalidParam`] if `current_start` is not the
+    /// start of the most recent page allocation of a region, and with
+    /// [`AllocError::NoMemory`] if the extra pages would run into the bytes
+    /// area or a reserved span, or once sealed.
+    pub fn grow_pages(
+        &mut self,
+        current_start: usize,
+        current_pages: usize,
+        extra_pages: usize,
+    ) -> AllocResult<usize> {
+        if self.sealed {
+            return Err(AllocError::NoMemory);
+        }
+        let size = PAGE_SIZE
+            .checked_mul(extra_pages)
+            .ok_or(AllocError::NoMemory)?;
+        let reserved = &self.reserved;
+        let region = self.regions[..self.regions_len]
+            .iter_mut()
+            .find(|r| r.page_next == current_start && current_start < r.end)
+            .ok_or(AllocError::InvalidParam)?;
+        let new_start = current_start
+            .checked_sub(size)
+            .filter(|&start| start >= region.byte_next)
+            .filter(|&start| reserved.find_overlap(start, current_start).is_none())
+            .ok_or(AllocError::NoMemory)?;
+        region.page_next = new_start;
+        self.pages_used += extra_pages;
+        self.peak_pages_used = self.peak_pages_used.max(self.used_pages());
+        self.forget_page_block(current_start, current_pages);
+        self.record_page_block(new_start, current_pages + extra_pages);
+        self.after_change();
+        Ok(new_start)
+    }
+
+    /// Fills the used part of every bytes area with [`POISON_BYTE`], so that
+    /// dangling reads stand out in a memory dump.
+    ///
+    /// Only the part above the front pages is poisoned, as it is the only
+    /// part reused. Reserved spans in it are left untouched.
+    #[cfg(debug_assertions)]
+    fn poison_bytes(&mut self) {
+        for region in self.regions() {
+            for (start, end) in self.reserved.gaps(region.byte_floor, region.byte_next) {
+                unsafe { core::ptr::write_bytes(start as *mut u8, POISON_BYTE, end - start) };
+            }
+        }
+    }
+
+    /// Turns the free lists on or off.
+    ///
+    /// When on, a freed block of at least one word is put on the list of its
+    /// size, and allocations of that size take from the list before bumping
+    /// `b_pos`. Up to [`FREE_LIST_SIZES`] sizes are kept; blocks of other
+    /// sizes are only reclaimed with the whole bytes area, as usual. Turning
+    /// the lists off forgets the blocks on them.
+    pub fn set_free_list(&mut self, enabled: bool) {
+        self.free.enabled = enabled;
+        if !enabled {
+            self.free.clear();
+        }
+    }
+
+    /// Sets the [`ByteAllocator::available_bytes`] level below which the
+    /// low-memory callback fires. Zero disables it.
+    pub fn set_low_memory_threshold(&mut self, bytes: usize) {
+        self.low_memory_threshold = bytes;
+        self.low_memory_armed = self.available_bytes() >= bytes;
+    }
+
+    /// Registers `f` to be called when an allocation drops the available
+    /// bytes below the low-memory threshold.
+    ///
+    /// It fires once, then stays quiet until the available bytes rise back
+    /// to the threshold.
+    pub fn on_low_memory(&mut self, f: fn()) {
+        self.low_memory_callback = Some(f);
+    }
+
+    /// Reads the lock-free mirror of the usage counters.
+    ///
+    /// The mirror is refreshed after every operation, so it may be sampled
+    /// by telemetry readers that do not own the allocator. It is only
+    /// consistent with [`EarlyAllocator::stats`] once some operation (e.g.
+    /// [`BaseAllocator::init`]) has run.
+    #[cfg(feature = "atomic-stats")]
+    pub fn stats_relaxed(&self) -> EarlyAllocStats {
+        self.mirror.load()
+    }
+
+    /// Returns the live page blocks as `(start, num_pages)`, in allocation
+    /// order, for debug dumps.
+    ///
+    /// Only the first [`MAX_PAGE_BLOCKS`] blocks live at once are recorded,
+    /// and a block is forgotten once given back with the same start and
+    /// size.
+    #[cfg(feature = "page-blocks")]
+    pub fn allocated_pages(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.page_blocks.blocks[..self.page_blocks.len]
+            .iter()
+            .copied()
+    }
+
+    #[cfg(feature = "page-blocks")]
+    fn record_page_block(&mut self, start: usize, num_pages: usize) {
+        self.page_blocks.push(start, num_pages);
+    }
+
+    #[cfg(not(feature = "page-blocks"))]
+    #[inline(always)]
+    fn record_page_block(&mut self, _start: usize, _num_pages: usize) {}
+
+    #[cfg(feature = "page-blocks")]
+    fn forget_page_block(&mut self, start: usize, num_pages: usize) {
+        self.page_blocks.remove(start, num_pages);
+    }
+
+    #[cfg(not(feature = "page-blocks"))]
+    #[inline(always)]
+    fn forget_page_block(&mut self, _start: usize, _num_pages: usize) {}
+
+    /// Fires the low-memory callback, or re-arms it once memory recovers.
+    fn check_low_memory(&mut self) {
+        let low = self.available_bytes() < self.low_memory_threshold;
+        if !low {
+            self.low_memory_armed = true;
+        } else if self.low_memory_armed {
+            self.low_memory_armed = false;
+            if let Some(callback) = self.low_memory_callback {
+                callback();
+            }
+        }
+    }
+
+    /// Runs the bookkeeping that follows every state change.
+    fn after_change(&mut self) {
+        debug_assert_eq!(
+            self.used_bytes() + self.available_bytes() + self.unlisted_bytes(),
+            self.total_bytes(),
+            "byte accounting is off",
+        );
+        self.publish_stats();
+        self.check_low_memory();
+    }
+
+    /// Returns the bytes counted neither as used nor as available: the page
+    /// areas below `end`, the front pages, and the reserved spans in the
+    /// free gaps.
+    fn unlisted_bytes(&self) -> usize {
+        let unlisted = |byte_next: usize, page_next: usize, end: usize| {
+            end.saturating_sub(page_next) + self.reserved.overlap_len(byte_next, page_next)
+        };
+        let regions: usize = self
+            .regions()
+            .iter()
+            .map(|r| unlisted(r.byte_next, r.page_next, r.end))
+            .sum();
+        regions + self.front_page_bytes
+    }
+
+    #[cfg(feature = "atomic-stats")]
+    fn publish_stats(&self) {
+        self.mirror.store(self.stats());
+    }
+
+    #[cfg(not(feature = "atomic-stats"))]
+    #[inline(always)]
+    fn publish_stats(&self) {}
+
+    /// Moves `b_pos` so that the block at `ptr`, if it is the most recent one
+    /// of its region, fits `new_layout`.
+    fn resize_in_place(
+        &mut self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Option<NonNull<u8>> {
+        let addr = ptr.as_ptr() as usize;
+        if old_layout.size() == 0 || new_layout.size() == 0 || addr % new_layout.align() != 0 {
+            return None;
+        }
+        let new_end = addr.checked_add(new_layout.size())?;
+        if self.sealed && new_layout.size() > old_layout.size() {
+            return None;
+        }
+        if self.reserved.find_overlap(addr, new_end).is_some() {
+            return None;
+        }
+        let (byte_next, page_next) = self.last_block_cursor(addr + old_layout.size())?;
+        if new_end > page_next {
+            return None;
+        }
+        *byte_next = new_end;
+        self.requested_bytes =
+            self.requested_bytes.saturating_sub(old_layout.size()) + new_layout.size();
+        self.peak_bytes_used = self.peak_bytes_used.max(self.used_bytes());
+        self.after_change();
+        Some(ptr)
+    }
+
+    /// Finds the region whose bytes area ends at `end`, returning its `b_pos`
+    /// and `p_pos`.
+    fn last_block_cursor(&mut self, end: usize) -> Option<(&mut usize, usize)> {
+        self.regions_mut()
+            .iter_mut()
+            .find(|r| r.byte_next == end)
+            .map(|r| (&mut r.byte_next, r.page_next))
+    }
+
+    /// Returns the size of the gap `[byte_next, page_next)`, minus the
+    /// reserved spans inside it.
+    fn free_gap(&self, byte_next: usize, page_next: usize) -> usize {
+        page_next
+            .saturating_sub(byte_next)
+            .saturating_sub(self.reserved.overlap_len(byte_next, page_next))
+    }
+
+    fn primary(&self) -> &Region {
+        &self.regions[0]
+    }
+
+    fn regions(&self) -> &[Region] {
+        &self.regions[..self.regions_len]
+    }
+
+    fn regions_mut(&mut self) -> &mut [Region] {
+        &mut self.regions[..self.regions_len]
+    }
+}
+
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> Default
+    for EarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The alternate form (`{:#?}`) draws the double-ended layout of every
+/// region.
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> fmt::Debug
+    for EarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let primary = self.primary();
+        if !f.alternate() {
+            return f
+                .debug_struct("EarlyAllocator")
+                .field("start", &format_args!("{:#x}", primary.start))
+                .field("b_pos", &format_args!("{:#x}", primary.byte_next))
+                .field("p_pos", &format_args!("{:#x}", primary.page_next))
+                .field("end", &format_args!("{:#x}", primary.end))
+                .field("byte_count", &self.byte_count)
+                .field("page_count", &self.page_count)
+                .field("extra", &&self.regions()[1..])
+                .finish();
+        }
+        writeln!(
+            f,
+            "EarlyAllocator (byte_count: {}, page_count: {})",
+            self.byte_count, self.page_count
+        )?;
+        for region in self.regions() {
+            writeln!(f, "  [ bytes-used | avail-area | pages-used ]")?;
+            writeln!(f, "  |            | -->    <-- |            |")?;
+            writeln!(
+                f,
+                "  start = {:#x}, b_pos = {:#x}, p_pos = {:#x}, end = {:#x}",
+                region.start, region.byte_next, region.page_next, region.end
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "[{:#x}, b_pos {:#x}, p_pos {:#x}, {:#x})",
+            self.start, self.byte_next, self.page_next, self.end
+        )
+    }
 }
 
-impl BaseAllocator for EarlyAllocator {
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> BaseAllocator
+    for EarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    fn init(&mut self, start: usize, size: usize) {
+        self.regions[0] = Region::new(start, start + size);
+        self.regions_len = 1;
+        self.reserved = Reservations::new();
+        self.sealed = false;
+        self.reset();
+    }
+
+    /// Adds `[start, start + size)` to the allocator.
+    ///
+    /// If the new block begins exactly at `end` and no pages have been
+    /// allocated yet, the primary region is simply extended. Otherwise the
+    /// block is recorded as an extra region.
+    fn add_memory(&mut self, start: usize, size: usize) -> AllocResult {
+        let end = match start.checked_add(size) {
+            Some(end) if size > 0 => end,
+            _ => return Err(AllocError::InvalidParam),
+        };
+        let primary = &mut self.regions[0];
+        if start == primary.end && primary.page_next == primary.end {
+            primary.end = end;
+            primary.page_next = end;
+            self.after_change();
+            return Ok(());
+        }
+        if self.regions().iter().any(|r| r.overlaps(start, end)) {
+            return Err(AllocError::MemoryOverlap);
+        }
+        if self.regions_len == MAX_REGIONS {
+            return Err(AllocError::NoMemory);
+        }
+        self.regions[self.regions_len] = Region::new(start, end);
+        self.regions_len += 1;
+        self.after_change();
+        Ok(())
+    }
+}
+
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> ByteAllocator
+    for EarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    /// Zero-sized layouts get a dangling, well-aligned pointer without
+    /// touching the bytes area, following the convention of `alloc::alloc`.
+    /// Such pointers may be passed to `dealloc`, which ignores them.
+    fn alloc(&mut self, layout: Layout) -> AllocResult<NonNull<u8>> {
+        if self.sealed {
+            return Err(AllocError::NoMemory);
+        }
+        if layout.size() == 0 {
+            return NonNull::new(layout.align() as *mut u8).ok_or(AllocError::InvalidParam);
+        }
+        if let Some(block) = self.free.pop(layout) {
+            self.byte_count += 1;
+            self.requested_bytes += layout.size();
+            self.after_change();
+            return NonNull::new(block as *mut u8).ok_or(AllocError::NoMemory);
+        }
+        let reserved = &self.reserved;
+        let region = self.regions[..self.regions_len].iter_mut().find_map(|r| {
+            bump_bytes(r.byte_next, r.page_next, layout, reserved).map(|pos| (r, pos))
+        });
+        let (region, (start, end)) = region.ok_or(AllocError::NoMemory)?;
+        region.byte_next = end;
+        self.byte_count += 1;
+        self.requested_bytes += layout.size();
+        self.peak_bytes_used = self.peak_bytes_used.max(self.used_bytes());
+        self.after_change();
+        NonNull::new(start as *mut u8).ok_or(AllocError::NoMemory)
+    }
+
+    /// Unbalanced frees are ignored, see [`EarlyAllocator::try_dealloc`].
+    fn dealloc(&mut self, pos: NonNull<u8>, layout: Layout) {
+        let _ = self.try_dealloc(pos, layout);
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.regions().iter().map(|r| r.end - r.start).sum()
+    }
+
+    /// Pages from [`EarlyAllocator::alloc_pages_front`] are not included,
+    /// though they sit in the bytes area.
+    ///
+    /// The result saturates, so it never exceeds [`ByteAllocator::total_bytes`]
+    /// even if the accounting went wrong.
+    fn used_bytes(&self) -> usize {
+        let used: usize = self
+            .regions()
+            .iter()
+            .map(|r| r.byte_next.saturating_sub(r.start))
+            .sum();
+        used.saturating_sub(self.front_page_bytes)
+            .min(self.total_bytes())
+    }
+
+    fn available_bytes(&self) -> usize {
+        self.regions()
+            .iter()
+            .map(|r| self.free_gap(r.byte_next, r.page_next))
+            .sum()
+    }
+}
+
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> PageAllocator
+    for EarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    const PAGE_SIZE: usize = PAGE_SIZE;
+
+    fn alloc_pages(&mut self, num_pages: usize, align_pow2: usize) -> AllocResult<usize> {
+        if self.sealed {
+            return Err(AllocError::NoMemory);
+        }
+        if !align_pow2.is_power_of_two() {
+            return Err(AllocError::InvalidParam);
+        }
+        let size = PAGE_SIZE
+            .checked_mul(num_pages)
+            .ok_or(AllocError::NoMemory)?;
+        let reserved = &self.reserved;
+        let region = self.regions[..self.regions_len].iter_mut().find_map(|r| {
+            bump_pages(r.byte_next, r.page_next, size, align_pow2, reserved).map(|pos| (r, pos))
+        });
+        let (region, start) = region.ok_or(AllocError::NoMemory)?;
+        region.page_next = start;
+        self.page_count += 1;
+        self.pages_used += num_pages;
+        self.peak_pages_used = self.peak_pages_used.max(self.used_pages());
+        self.record_page_block(start, num_pages);
+        self.after_change();
+        Ok(start)
+    }
+
+    /// Unbalanced frees are ignored, see [`EarlyAllocator::try_dealloc_pages`].
+    fn dealloc_pages(&mut self, pos: usize, num_pages: usize) {
+        let _ = self.try_dealloc_pages(pos, num_pages);
+    }
+
+    fn total_pages(&self) -> usize {
+        self.regions()
+            .iter()
+            .map(|r| (r.end - r.start) / PAGE_SIZE)
+            .sum()
+    }
+
+    fn used_pages(&self) -> usize {
+        self.pages_used
+    }
+
+    fn available_pages(&self) -> usize {
+        self.regions()
+            .iter()
+            .map(|r| self.free_gap(r.byte_next, r.page_next) / PAGE_SIZE)
+            .sum()
+    }
 }
 
-impl ByteAllocator for EarlyAllocator {
+/// Carves `layout` forward from `byte_next`, returning the `[start, end)` of
+/// the new block if it stays below `page_next` without overflowing.
+///
+/// Reserved spans in the way are skipped.
+fn bump_bytes(
+    mut byte_next: usize,
+    page_next: usize,
+    layout: Layout,
+    reserved: &Reservations,
+) -> Option<(usize, usize)> {
+    loop {
+        let start = align_up_checked(byte_next, layout.align())?;
+        let end = start.checked_add(layout.size())?;
+        match reserved.find_overlap(start, end) {
+            Some((_, reserved_end)) => byte_next = reserved_end,
+            None => return (end <= page_next).then_some((start, end)),
+        }
+    }
 }
 
-impl PageAllocator for EarlyAllocator {
+/// Carves `size` bytes backward from `page_next`, returning the start of the
+/// new block if it stays above `byte_next` without underflowing.
+///
+/// Reserved spans in the way are skipped.
+fn bump_pages(
+    byte_next: usize,
+    mut page_next: usize,
+    size: usize,
+    align_pow2: usize,
+    reserved: &Reservations,
+) -> Option<usize> {
+    loop {
+        let start = align_down(page_next.checked_sub(size)?, align_pow2);
+        match reserved.find_overlap(start, start + size) {
+            Some((reserved_start, _)) => page_next = reserved_start,
+            None => return (start >= byte_next).then_some(start),
+        }
+    }
 }
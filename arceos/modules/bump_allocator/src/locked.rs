@@ -0,0 +1,71 @@
+use core::alloc::{GlobalAlloc, Layout};
+use core::ptr::{self, NonNull};
+
+use allocator::{BaseAllocator, ByteAllocator};
+use kspin::{SpinNoIrq, SpinNoIrqGuard};
+
+use crate::EarlyAllocator;
+
+/// An [`EarlyAllocator`] behind a spinlock, usable as the
+/// `#[global_allocator]` during the earliest boot phase.
+///
+/// Only the bytes area is reachable through [`GlobalAlloc`]; pages can still
+/// be allocated through [`LockedEarlyAllocator::lock`]. `MAX_REGIONS` is
+/// passed on to the inner [`EarlyAllocator`].
+pub struct LockedEarlyAllocator<const PAGE_SIZE: usize, const MAX_REGIONS: usize = 1> {
+    inner: SpinNoIrq<EarlyAllocator<PAGE_SIZE, MAX_REGIONS>>,
+}
+
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize>
+    LockedEarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    /// Creates an empty allocator. It must be initialized by
+    /// [`LockedEarlyAllocator::init`] before use.
+    pub const fn new() -> Self {
+        Self {
+            inner: SpinNoIrq::new(EarlyAllocator::new()),
+        }
+    }
+
+    /// Initializes the allocator with the given region.
+    pub fn init(&self, start: usize, size: usize) {
+        self.inner.lock().init(start, size);
+    }
+
+    /// Locks the allocator for direct access.
+    pub fn lock(&self) -> SpinNoIrqGuard<'_, EarlyAllocator<PAGE_SIZE, MAX_REGIONS>> {
+        self.inner.lock()
+    }
+}
+
+impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> Default
+    for LockedEarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const PAGE_SIZE: usize, const MAX_REGIONS: usize> GlobalAlloc
+    for LockedEarlyAllocator<PAGE_SIZE, MAX_REGIONS>
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        match self.inner.lock().alloc(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        match self.inner.lock().alloc_zeroed(layout) {
+            Ok(ptr) => ptr.as_ptr(),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(ptr) = NonNull::new(ptr) {
+            self.inner.lock().dealloc(ptr, layout);
+        }
+    }
+}
@@ -0,0 +1,1288 @@
+use core::alloc::Layout;
+use core::ptr::NonNull;
+
+use allocator::{AllocError, BaseAllocator, ByteAllocator, PageAllocator};
+
+use crate::{EarlyAllocator, LockedEarlyAllocator, PageAddr, Region, FREE_LIST_SIZES, POISON_BYTE};
+
+const PAGE_SIZE: usize = 0x1000;
+const ARENA_PAGES: usize = 16;
+
+#[repr(C, align(4096))]
+struct Arena([u8; ARENA_PAGES * PAGE_SIZE]);
+
+fn arena() -> Box<Arena> {
+    Box::new(Arena([0; ARENA_PAGES * PAGE_SIZE]))
+}
+
+fn base(arena: &Arena) -> usize {
+    arena.0.as_ptr() as usize
+}
+
+#[test]
+fn add_memory_extends_contiguous_region() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    assert_eq!(alloc.total_bytes(), 4 * PAGE_SIZE);
+    assert_eq!(alloc.total_pages(), 4);
+
+    alloc
+        .add_memory(start + 4 * PAGE_SIZE, 4 * PAGE_SIZE)
+        .unwrap();
+    assert_eq!(alloc.total_bytes(), 8 * PAGE_SIZE);
+    assert_eq!(alloc.total_pages(), 8);
+    assert_eq!(alloc.available_pages(), 8);
+
+    let pos = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(pos, start + 7 * PAGE_SIZE);
+}
+
+#[test]
+fn add_memory_rejects_overlapping_region() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 2>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    alloc
+        .add_memory(start + 8 * PAGE_SIZE, 2 * PAGE_SIZE)
+        .unwrap();
+
+    let res = alloc.add_memory(start + 2 * PAGE_SIZE, 4 * PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::MemoryOverlap)));
+    let res = alloc.add_memory(start + 9 * PAGE_SIZE, 2 * PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::MemoryOverlap)));
+    assert_eq!(alloc.total_pages(), 6);
+}
+
+#[test]
+fn add_memory_records_disjoint_regions() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 3>::new();
+    alloc.init(start, 2 * PAGE_SIZE);
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    // Contiguous with `end`, but behind a live page: kept as its own region.
+    alloc
+        .add_memory(start + 2 * PAGE_SIZE, 2 * PAGE_SIZE)
+        .unwrap();
+    alloc
+        .add_memory(start + 6 * PAGE_SIZE, 2 * PAGE_SIZE)
+        .unwrap();
+    assert_eq!(alloc.total_pages(), 6);
+    assert_eq!(alloc.available_pages(), 5);
+    assert_eq!(alloc.available_bytes(), 5 * PAGE_SIZE);
+}
+
+#[test]
+fn add_memory_rejects_too_many_regions() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 5>::new();
+    alloc.init(start, PAGE_SIZE);
+    for i in 0..4 {
+        alloc
+            .add_memory(start + (2 * i + 2) * PAGE_SIZE, PAGE_SIZE)
+            .unwrap();
+    }
+    let res = alloc.add_memory(start + 15 * PAGE_SIZE, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::NoMemory)));
+}
+
+#[test]
+fn bytes_spill_into_next_region() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 2>::new();
+    alloc.init(start, PAGE_SIZE);
+    alloc.add_memory(start + 4 * PAGE_SIZE, PAGE_SIZE).unwrap();
+
+    let layout = Layout::from_size_align(PAGE_SIZE / 2, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap().as_ptr() as usize;
+    let b = alloc.alloc(layout).unwrap().as_ptr() as usize;
+    let c = alloc.alloc(layout).unwrap().as_ptr() as usize;
+    assert_eq!(a, start);
+    assert_eq!(b, start + PAGE_SIZE / 2);
+    assert_eq!(c, start + 4 * PAGE_SIZE);
+    assert_eq!(alloc.used_bytes(), 3 * PAGE_SIZE / 2);
+    assert_eq!(alloc.available_bytes(), PAGE_SIZE / 2);
+
+    for pos in [a, b, c] {
+        alloc.dealloc(NonNull::new(pos as *mut u8).unwrap(), layout);
+    }
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.available_bytes(), 2 * PAGE_SIZE);
+}
+
+#[test]
+fn pages_spill_into_next_region() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 2>::new();
+    alloc.init(start, 2 * PAGE_SIZE);
+    alloc
+        .add_memory(start + 8 * PAGE_SIZE, 4 * PAGE_SIZE)
+        .unwrap();
+
+    assert_eq!(alloc.alloc_pages(2, PAGE_SIZE).unwrap(), start);
+    assert_eq!(
+        alloc.alloc_pages(1, PAGE_SIZE).unwrap(),
+        start + 11 * PAGE_SIZE
+    );
+    assert_eq!(
+        alloc.alloc_pages(3, PAGE_SIZE).unwrap(),
+        start + 8 * PAGE_SIZE
+    );
+    assert!(matches!(
+        alloc.alloc_pages(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    assert_eq!(alloc.available_pages(), 0);
+}
+
+#[test]
+fn alloc_zeroed_clears_reused_bytes() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(256, 16).unwrap();
+    let dirty = alloc.alloc(layout).unwrap();
+    unsafe { core::ptr::write_bytes(dirty.as_ptr(), 0xaa, layout.size()) };
+    alloc.dealloc(dirty, layout);
+
+    let zeroed = alloc.alloc_zeroed(layout).unwrap();
+    assert_eq!(zeroed, dirty);
+    let bytes = unsafe { core::slice::from_raw_parts(zeroed.as_ptr(), layout.size()) };
+    assert!(bytes.iter().all(|&b| b == 0));
+}
+
+#[test]
+fn alloc_zeroed_fails_without_writing() {
+    let mut arena = arena();
+    arena.0.fill(0xaa);
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, PAGE_SIZE);
+
+    let layout = Layout::from_size_align(2 * PAGE_SIZE, 8).unwrap();
+    assert!(matches!(
+        alloc.alloc_zeroed(layout),
+        Err(AllocError::NoMemory)
+    ));
+    assert!(arena.0.iter().all(|&b| b == 0xaa));
+}
+
+#[test]
+fn double_free_keeps_allocator_usable() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let ptr = alloc.alloc(layout).unwrap();
+    alloc.dealloc(ptr, layout);
+    alloc.dealloc(ptr, layout);
+    assert!(matches!(
+        alloc.try_dealloc(ptr, layout),
+        Err(AllocError::NotAllocated)
+    ));
+    assert_eq!(alloc.used_bytes(), 0);
+
+    // The bytes area is still reclaimed once the next allocation is freed.
+    let ptr = alloc.alloc(layout).unwrap();
+    assert_eq!(ptr.as_ptr() as usize, start);
+    alloc.dealloc(ptr, layout);
+    assert_eq!(alloc.used_bytes(), 0);
+}
+
+#[test]
+fn unbalanced_page_free_is_ignored() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let pos = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    alloc.dealloc_pages(pos, 1);
+    alloc.dealloc_pages(pos, 1);
+    assert!(matches!(
+        alloc.try_dealloc_pages(pos, 1),
+        Err(AllocError::NotAllocated)
+    ));
+    assert_eq!(alloc.used_pages(), 0);
+
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.used_pages(), 1);
+}
+
+#[test]
+fn alloc_near_address_space_top_fails_cleanly() {
+    // Never dereferenced: the allocator only does address arithmetic here.
+    let start = usize::MAX - 0xff;
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 0xff);
+
+    let aligned = Layout::from_size_align(8, 0x1000).unwrap();
+    assert!(matches!(alloc.alloc(aligned), Err(AllocError::NoMemory)));
+    let large = Layout::from_size_align(0x200, 1).unwrap();
+    assert!(matches!(alloc.alloc(large), Err(AllocError::NoMemory)));
+    assert_eq!(alloc.used_bytes(), 0);
+
+    let small = Layout::from_size_align(0x10, 1).unwrap();
+    assert_eq!(alloc.alloc(small).unwrap().as_ptr() as usize, start);
+}
+
+#[test]
+fn zero_sized_alloc_is_dangling() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let zst = Layout::from_size_align(0, 64).unwrap();
+    let a = alloc.alloc(zst).unwrap();
+    let b = alloc.alloc(zst).unwrap();
+    assert_eq!(a.as_ptr() as usize, 64);
+    assert_eq!(a, b);
+    assert_eq!(alloc.used_bytes(), 0);
+
+    let layout = Layout::from_size_align(16, 8).unwrap();
+    let ptr = alloc.alloc(layout).unwrap();
+    assert_eq!(ptr.as_ptr() as usize, start);
+
+    // Freeing the zero-sized blocks must not release the live allocation.
+    alloc.dealloc(a, zst);
+    alloc.dealloc(b, zst);
+    assert_eq!(alloc.used_bytes(), 16);
+    alloc.dealloc(ptr, layout);
+    assert_eq!(alloc.used_bytes(), 0);
+}
+
+#[test]
+fn reset_restores_empty_state() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 2>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    alloc
+        .add_memory(start + 8 * PAGE_SIZE, 2 * PAGE_SIZE)
+        .unwrap();
+
+    let layout = Layout::from_size_align(PAGE_SIZE, 8).unwrap();
+    while alloc.alloc(layout).is_ok() {}
+    assert!(matches!(
+        alloc.alloc_pages(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+
+    alloc.reset();
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.used_pages(), 0);
+    assert_eq!(alloc.total_bytes(), 6 * PAGE_SIZE);
+    assert_eq!(alloc.available_bytes(), 6 * PAGE_SIZE);
+    assert_eq!(alloc.alloc(layout).unwrap().as_ptr() as usize, start);
+    assert_eq!(
+        alloc.alloc_pages(1, PAGE_SIZE).unwrap(),
+        start + 3 * PAGE_SIZE
+    );
+}
+
+#[test]
+fn rollback_frees_allocations_after_checkpoint() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 2>::new();
+    alloc.init(start, PAGE_SIZE);
+    alloc.add_memory(start + 4 * PAGE_SIZE, PAGE_SIZE).unwrap();
+
+    let layout = Layout::from_size_align(PAGE_SIZE / 4, 8).unwrap();
+    let kept = alloc.alloc(layout).unwrap();
+    let marker = alloc.checkpoint();
+    for _ in 0..5 {
+        alloc.alloc(layout).unwrap();
+    }
+    assert_eq!(alloc.used_bytes(), 6 * PAGE_SIZE / 4);
+
+    alloc.rollback(marker).unwrap();
+    assert_eq!(alloc.used_bytes(), PAGE_SIZE / 4);
+    let next = alloc.alloc(layout).unwrap();
+    assert_eq!(next.as_ptr() as usize, start + PAGE_SIZE / 4);
+
+    // Exactly two allocations are live again.
+    alloc.dealloc(next, layout);
+    assert_eq!(alloc.used_bytes(), 2 * PAGE_SIZE / 4);
+    alloc.dealloc(kept, layout);
+    assert_eq!(alloc.used_bytes(), 0);
+}
+
+#[test]
+fn rollback_rejects_stale_marker() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let ptr = alloc.alloc(layout).unwrap();
+    let marker = alloc.checkpoint();
+    alloc.dealloc(ptr, layout);
+
+    assert!(matches!(
+        alloc.rollback(marker),
+        Err(AllocError::InvalidParam)
+    ));
+    assert_eq!(alloc.used_bytes(), 0);
+}
+
+#[test]
+fn peak_usage_survives_dealloc() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(128, 8).unwrap();
+    let ptrs: Vec<_> = (0..10).map(|_| alloc.alloc(layout).unwrap()).collect();
+    let pages: Vec<_> = (0..3)
+        .map(|_| alloc.alloc_pages(1, PAGE_SIZE).unwrap())
+        .collect();
+    assert_eq!(alloc.peak_used_bytes(), 10 * 128);
+    assert_eq!(alloc.peak_used_pages(), 3);
+
+    for ptr in ptrs {
+        alloc.dealloc(ptr, layout);
+    }
+    for pos in pages {
+        alloc.dealloc_pages(pos, 1);
+    }
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.peak_used_bytes(), 10 * 128);
+    assert_eq!(alloc.peak_used_pages(), 3);
+
+    alloc.alloc(layout).unwrap();
+    assert_eq!(alloc.peak_used_bytes(), 10 * 128);
+}
+
+#[test]
+fn alloc_pages_validates_alignment() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    assert!(matches!(
+        alloc.alloc_pages(1, 0),
+        Err(AllocError::InvalidParam)
+    ));
+    assert!(matches!(
+        alloc.alloc_pages(1, 3),
+        Err(AllocError::InvalidParam)
+    ));
+    assert_eq!(alloc.used_pages(), 0);
+    assert_eq!(alloc.available_pages(), 4);
+
+    let pos = alloc.alloc_pages(1, 4096).unwrap();
+    assert_eq!(pos % 4096, 0);
+}
+
+#[test]
+fn oversized_page_request_fails_cleanly() {
+    // Place the region at the bottom of the address space so that an
+    // unchecked subtraction would wrap around.
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(PAGE_SIZE, 4 * PAGE_SIZE);
+
+    let res = alloc.alloc_pages(8, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::NoMemory)));
+    let res = alloc.alloc_pages(usize::MAX, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::NoMemory)));
+    assert_eq!(alloc.available_pages(), 4);
+    assert_eq!(alloc.used_pages(), 0);
+
+    assert_eq!(alloc.alloc_pages(4, PAGE_SIZE).unwrap(), PAGE_SIZE);
+}
+
+#[test]
+fn stats_match_accessors() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(100, 16).unwrap();
+    let ptr = alloc.alloc(layout).unwrap();
+    alloc.alloc(layout).unwrap();
+    alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    alloc.dealloc(ptr, layout);
+
+    let stats = alloc.stats();
+    assert_eq!(stats.total_bytes, alloc.total_bytes());
+    assert_eq!(stats.used_bytes, alloc.used_bytes());
+    assert_eq!(stats.available_bytes, alloc.available_bytes());
+    assert_eq!(stats.total_pages, alloc.total_pages());
+    assert_eq!(stats.used_pages, alloc.used_pages());
+    assert_eq!(stats.available_pages, alloc.available_pages());
+    assert_eq!(stats.byte_count, 1);
+    assert_eq!(stats.page_count, 1);
+    assert!(format!("{stats:?}").contains("byte_count: 1"));
+}
+
+#[test]
+fn locked_allocator_returns_null_when_exhausted() {
+    use core::alloc::GlobalAlloc;
+
+    let arena = arena();
+    let start = base(&arena);
+    let alloc = LockedEarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, PAGE_SIZE);
+
+    let layout = Layout::from_size_align(PAGE_SIZE / 2, 8).unwrap();
+    unsafe {
+        let a = alloc.alloc(layout);
+        assert_eq!(a as usize, start);
+        let b = alloc.alloc_zeroed(layout);
+        assert_eq!(b as usize, start + PAGE_SIZE / 2);
+        assert!(alloc.alloc(layout).is_null());
+
+        alloc.dealloc(a, layout);
+        alloc.dealloc(b, layout);
+    }
+    assert_eq!(alloc.lock().used_bytes(), 0);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn final_dealloc_poisons_bytes_area() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let small = Layout::from_size_align(10, 1).unwrap();
+    let aligned = Layout::from_size_align(32, 64).unwrap();
+    let a = alloc.alloc(small).unwrap();
+    let b = alloc.alloc(aligned).unwrap();
+    unsafe { core::ptr::write_bytes(b.as_ptr(), 0x11, aligned.size()) };
+
+    alloc.dealloc(a, small);
+    assert_eq!(arena.0[0], 0);
+    alloc.dealloc(b, aligned);
+    assert!(arena.0[..96].iter().all(|&b| b == POISON_BYTE));
+    assert_eq!(arena.0[96], 0);
+}
+
+#[cfg(debug_assertions)]
+#[test]
+fn poisoning_spares_reserved_spans() {
+    let mut arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    alloc.reserve(start + 64, 64).unwrap();
+    arena.0[64..128].fill(0xab);
+    let layout = Layout::from_size_align(128, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    assert_eq!(a.as_ptr() as usize, start + 128);
+
+    alloc.dealloc(a, layout);
+    assert!(arena.0[64..128].iter().all(|&b| b == 0xab));
+    assert!(arena.0[128..256].iter().all(|&b| b == POISON_BYTE));
+    assert_eq!(arena.0[256], 0);
+}
+
+#[test]
+fn used_pages_counts_pages_not_allocations() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 16 * PAGE_SIZE);
+    assert_eq!(
+        alloc.used_pages() + alloc.available_pages(),
+        alloc.total_pages()
+    );
+
+    let a = alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    alloc.alloc_pages(5, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.used_pages(), 8);
+    assert_eq!(alloc.available_pages(), 8);
+    assert_eq!(
+        alloc.used_pages() + alloc.available_pages(),
+        alloc.total_pages()
+    );
+    assert_eq!(alloc.stats().page_count, 2);
+
+    // Freed pages are no longer in use, but never become available again.
+    alloc.dealloc_pages(a, 3);
+    assert_eq!(alloc.used_pages(), 5);
+    assert_eq!(alloc.available_pages(), 8);
+
+    // The bytes area grows into the same gap.
+    let layout = Layout::from_size_align(PAGE_SIZE + 1, 8).unwrap();
+    alloc.alloc(layout).unwrap();
+    assert_eq!(alloc.available_pages(), 6);
+    assert!(alloc.used_pages() + alloc.available_pages() <= alloc.total_pages());
+}
+
+#[test]
+fn requested_bytes_exclude_padding() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    // Each block is padded from 1 up to the next 256-byte boundary.
+    let layout = Layout::from_size_align(1, 256).unwrap();
+    let ptrs: Vec<_> = (0..4).map(|_| alloc.alloc(layout).unwrap()).collect();
+    assert_eq!(alloc.requested_bytes(), 4);
+    assert_eq!(alloc.used_bytes(), 3 * 256 + 1);
+    assert_eq!(alloc.used_bytes() - alloc.requested_bytes(), 3 * 255);
+
+    alloc.dealloc(ptrs[0], layout);
+    assert_eq!(alloc.requested_bytes(), 3);
+    let marker = alloc.checkpoint();
+    alloc.alloc(layout).unwrap();
+    alloc.rollback(marker).unwrap();
+    assert_eq!(alloc.requested_bytes(), 3);
+    for &ptr in &ptrs[1..] {
+        alloc.dealloc(ptr, layout);
+    }
+    assert_eq!(alloc.requested_bytes(), 0);
+}
+
+#[test]
+fn with_region_needs_no_init() {
+    static REGION: EarlyAllocator<PAGE_SIZE> =
+        EarlyAllocator::with_region(0x10_0000, 4 * PAGE_SIZE);
+    assert_eq!(REGION.total_pages(), 4);
+    assert_eq!(REGION.available_bytes(), 4 * PAGE_SIZE);
+
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::with_region(start, 4 * PAGE_SIZE);
+    let layout = Layout::from_size_align(32, 8).unwrap();
+    assert_eq!(alloc.alloc(layout).unwrap().as_ptr() as usize, start);
+    assert_eq!(
+        alloc.alloc_pages(1, PAGE_SIZE).unwrap(),
+        start + 3 * PAGE_SIZE
+    );
+}
+
+#[test]
+fn alloc_pages_ptr_spans_whole_block() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let block = alloc.alloc_pages_ptr(2, PAGE_SIZE).unwrap();
+    assert_eq!(block.len(), 2 * PAGE_SIZE);
+    assert_eq!(block.as_ptr() as *mut u8 as usize, start + 2 * PAGE_SIZE);
+    assert_eq!(alloc.used_pages(), 2);
+}
+
+#[cfg(feature = "atomic-stats")]
+#[test]
+fn relaxed_stats_stay_in_sync() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+    assert_eq!(alloc.stats_relaxed(), alloc.stats());
+
+    let layout = Layout::from_size_align(48, 16).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    assert_eq!(alloc.stats_relaxed(), alloc.stats());
+    let b = alloc.alloc(layout).unwrap();
+    let pos = alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.stats_relaxed(), alloc.stats());
+    alloc.dealloc(a, layout);
+    assert_eq!(alloc.stats_relaxed(), alloc.stats());
+    alloc.dealloc(b, layout);
+    alloc.dealloc_pages(pos, 2);
+    assert_eq!(alloc.stats_relaxed(), alloc.stats());
+    assert_eq!(alloc.stats_relaxed().used_bytes, 0);
+}
+
+#[test]
+fn low_memory_callback_fires_once_and_rearms() {
+    use core::sync::atomic::{AtomicUsize, Ordering};
+
+    static FIRED: AtomicUsize = AtomicUsize::new(0);
+    fn on_low_memory() {
+        FIRED.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    alloc.set_low_memory_threshold(2 * PAGE_SIZE);
+    alloc.on_low_memory(on_low_memory);
+
+    let layout = Layout::from_size_align(PAGE_SIZE, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    let b = alloc.alloc(layout).unwrap();
+    assert_eq!(FIRED.load(Ordering::Relaxed), 0);
+    let c = alloc.alloc(layout).unwrap();
+    assert_eq!(FIRED.load(Ordering::Relaxed), 1);
+    let small = Layout::from_size_align(16, 8).unwrap();
+    let d = alloc.alloc(small).unwrap();
+    assert_eq!(FIRED.load(Ordering::Relaxed), 1);
+
+    for (ptr, layout) in [(a, layout), (b, layout), (c, layout), (d, small)] {
+        alloc.dealloc(ptr, layout);
+    }
+    assert_eq!(alloc.available_bytes(), 4 * PAGE_SIZE);
+    alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(FIRED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn debug_shows_cursor_positions() {
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(0x8000_0000, 4 * PAGE_SIZE);
+    // Only cursors move here, so no memory is touched.
+    let layout = Layout::from_size_align(0x100, 8).unwrap();
+    alloc.alloc(layout).unwrap();
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    let flat = format!("{alloc:?}");
+    assert!(flat.contains("start: 0x80000000"));
+    assert!(flat.contains("b_pos: 0x80000100"));
+    assert!(flat.contains("p_pos: 0x80003000"));
+    assert!(flat.contains("end: 0x80004000"));
+    assert!(flat.contains("byte_count: 1"));
+    assert!(flat.contains("page_count: 1"));
+
+    let drawn = format!("{alloc:#?}");
+    assert!(drawn.contains("[ bytes-used | avail-area | pages-used ]"));
+    assert!(drawn.contains("b_pos = 0x80000100, p_pos = 0x80003000"));
+}
+
+#[test]
+fn realloc_resizes_last_allocation_in_place() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let big = Layout::from_size_align(1024, 8).unwrap();
+    let small = Layout::from_size_align(100, 8).unwrap();
+    let ptr = alloc.alloc(big).unwrap();
+    let shrunk = alloc.realloc(ptr, big, small).unwrap();
+    assert_eq!(shrunk, ptr);
+    assert_eq!(alloc.used_bytes(), 100);
+    assert_eq!(alloc.requested_bytes(), 100);
+
+    let grown = alloc.realloc(shrunk, small, big).unwrap();
+    assert_eq!(grown, ptr);
+    assert_eq!(alloc.used_bytes(), 1024);
+
+    let next = alloc.alloc(small).unwrap();
+    assert_eq!(next.as_ptr() as usize, start + 1024);
+    alloc.dealloc(next, small);
+    alloc.dealloc(grown, big);
+    assert_eq!(alloc.used_bytes(), 0);
+}
+
+#[test]
+fn realloc_moves_earlier_allocation() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let old = Layout::from_size_align(16, 8).unwrap();
+    let new = Layout::from_size_align(64, 8).unwrap();
+    let first = alloc.alloc(old).unwrap();
+    unsafe { core::ptr::copy_nonoverlapping(b"0123456789abcdef".as_ptr(), first.as_ptr(), 16) };
+    let second = alloc.alloc(old).unwrap();
+
+    let moved = alloc.realloc(first, old, new).unwrap();
+    assert_eq!(moved.as_ptr() as usize, start + 32);
+    let bytes = unsafe { core::slice::from_raw_parts(moved.as_ptr(), 16) };
+    assert_eq!(bytes, b"0123456789abcdef");
+    assert_eq!(alloc.requested_bytes(), 16 + 64);
+
+    // A huge request fails and leaves the old block alone.
+    let huge = Layout::from_size_align(8 * PAGE_SIZE, 8).unwrap();
+    assert!(matches!(
+        alloc.realloc(second, old, huge),
+        Err(AllocError::NoMemory)
+    ));
+    assert_eq!(alloc.used_bytes(), 96);
+}
+
+#[test]
+fn reserved_span_is_skipped() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+
+    let dma = start + 2 * PAGE_SIZE;
+    alloc.reserve(dma, 3 * PAGE_SIZE).unwrap();
+    assert_eq!(alloc.available_bytes(), 5 * PAGE_SIZE);
+    assert_eq!(alloc.available_pages(), 5);
+
+    let layout = Layout::from_size_align(PAGE_SIZE + PAGE_SIZE / 2, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap().as_ptr() as usize;
+    let b = alloc.alloc(layout).unwrap().as_ptr() as usize;
+    assert_eq!(a, start);
+    assert_eq!(b, dma + 3 * PAGE_SIZE);
+
+    // Pages go below the reservation once the top is exhausted.
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+    alloc.reserve(dma, 3 * PAGE_SIZE).unwrap();
+    assert_eq!(
+        alloc.alloc_pages(3, PAGE_SIZE).unwrap(),
+        start + 5 * PAGE_SIZE
+    );
+    assert_eq!(alloc.alloc_pages(2, PAGE_SIZE).unwrap(), start);
+    assert!(matches!(
+        alloc.alloc_pages(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+}
+
+#[test]
+fn reserve_rejects_used_or_foreign_spans() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(PAGE_SIZE, 8).unwrap();
+    alloc.alloc(layout).unwrap();
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    let res = alloc.reserve(start, 2 * PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::MemoryOverlap)));
+    let res = alloc.reserve(start + 6 * PAGE_SIZE, 2 * PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::MemoryOverlap)));
+    let res = alloc.reserve(start + 7 * PAGE_SIZE, 2 * PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::InvalidParam)));
+
+    alloc.reserve(start + 2 * PAGE_SIZE, PAGE_SIZE).unwrap();
+    let res = alloc.reserve(start + 2 * PAGE_SIZE, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::MemoryOverlap)));
+
+    // The reservation stays in place across a reset.
+    alloc.reset();
+    alloc.alloc(layout).unwrap();
+    alloc.alloc(layout).unwrap();
+    assert_eq!(
+        alloc.alloc(layout).unwrap().as_ptr() as usize,
+        start + 3 * PAGE_SIZE
+    );
+}
+
+#[test]
+fn fragmentation_report() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    assert_eq!(alloc.utilization(), 0);
+    assert_eq!(alloc.largest_free_block(), 0);
+
+    alloc.init(start, 10 * PAGE_SIZE);
+    assert_eq!(alloc.utilization(), 0);
+    assert_eq!(alloc.largest_free_block(), 10 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(PAGE_SIZE, 8).unwrap();
+    alloc.alloc(layout).unwrap();
+    alloc.alloc(layout).unwrap();
+    alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.utilization(), 50);
+    assert_eq!(alloc.largest_free_block(), alloc.available_bytes());
+
+    // A reservation splits the gap into two smaller blocks.
+    alloc.reserve(start + 3 * PAGE_SIZE, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.utilization(), 60);
+    assert_eq!(alloc.largest_free_block(), 3 * PAGE_SIZE);
+}
+
+#[test]
+fn front_and_back_pages_meet_in_the_middle() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    for _ in 0..4 {
+        front.push(alloc.alloc_pages_front(1, PAGE_SIZE).unwrap());
+        back.push(alloc.alloc_pages(1, PAGE_SIZE).unwrap());
+    }
+    let expected_front: Vec<_> = (0..4).map(|i| start + i * PAGE_SIZE).collect();
+    let expected_back: Vec<_> = (0..4).map(|i| start + (7 - i) * PAGE_SIZE).collect();
+    assert_eq!(front, expected_front);
+    assert_eq!(back, expected_back);
+
+    assert_eq!(alloc.used_pages(), 8);
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.available_pages(), 0);
+    assert!(matches!(
+        alloc.alloc_pages_front(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    assert!(matches!(
+        alloc.alloc_pages(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+}
+
+#[test]
+fn freeing_bytes_keeps_front_pages() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    let pages = alloc.alloc_pages_front(2, PAGE_SIZE).unwrap();
+    assert_eq!(pages, start + PAGE_SIZE);
+    let b = alloc.alloc(layout).unwrap();
+    assert_eq!(b.as_ptr() as usize, start + 3 * PAGE_SIZE);
+    assert!(matches!(
+        alloc.alloc_pages_front(1, 3 * PAGE_SIZE),
+        Err(AllocError::InvalidParam)
+    ));
+
+    alloc.dealloc(a, layout);
+    alloc.dealloc(b, layout);
+    // The bytes area restarts right after the front pages, and the page
+    // before them stays lost until reset.
+    assert_eq!(alloc.used_bytes(), PAGE_SIZE);
+    assert_eq!(alloc.available_pages(), 5);
+    let c = alloc.alloc(layout).unwrap();
+    assert_eq!(c, b);
+    alloc.dealloc(c, layout);
+
+    let marker = alloc.checkpoint();
+    alloc.alloc_pages_front(1, PAGE_SIZE).unwrap();
+    assert!(matches!(
+        alloc.rollback(marker),
+        Err(AllocError::InvalidParam)
+    ));
+
+    alloc.reset();
+    assert_eq!(alloc.used_bytes(), 0);
+    assert_eq!(alloc.used_pages(), 0);
+    assert_eq!(alloc.alloc(layout).unwrap(), a);
+}
+
+#[test]
+fn alloc_aligned_bumps_to_alignment() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let small = alloc.alloc_aligned(24, 8).unwrap();
+    assert_eq!(small.as_ptr() as usize, start);
+    let ring = alloc.alloc_aligned(256, PAGE_SIZE).unwrap();
+    assert_eq!(ring.as_ptr() as usize, start + PAGE_SIZE);
+    assert_eq!(alloc.used_bytes(), PAGE_SIZE + 256);
+
+    for align in [0, 3, 24] {
+        assert!(matches!(
+            alloc.alloc_aligned(16, align),
+            Err(AllocError::InvalidParam)
+        ));
+    }
+    assert_eq!(alloc.stats().byte_count, 2);
+}
+
+#[test]
+fn free_list_reuses_blocks_of_the_same_size() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    let small = Layout::from_size_align(32, 8).unwrap();
+    let large = Layout::from_size_align(64, 8).unwrap();
+
+    // Off by default: freed blocks wait for the whole area.
+    let keep = alloc.alloc(small).unwrap();
+    let a = alloc.alloc(small).unwrap();
+    alloc.dealloc(a, small);
+    assert_ne!(alloc.alloc(small).unwrap(), a);
+
+    alloc.set_free_list(true);
+    let b = alloc.alloc(small).unwrap();
+    let c = alloc.alloc(small).unwrap();
+    alloc.dealloc(b, small);
+    alloc.dealloc(c, small);
+    let used = alloc.used_bytes();
+    assert_ne!(alloc.alloc(large).unwrap(), c);
+    assert_eq!(alloc.alloc(small).unwrap(), c);
+    assert_eq!(alloc.alloc(small).unwrap(), b);
+    assert_eq!(alloc.used_bytes(), used + 64);
+
+    // Over-aligned requests don't take a block that would misalign them.
+    let d = alloc.alloc(small).unwrap();
+    alloc.dealloc(d, small);
+    let aligned = Layout::from_size_align(32, PAGE_SIZE).unwrap();
+    if d.as_ptr() as usize % PAGE_SIZE != 0 {
+        assert_ne!(alloc.alloc(aligned).unwrap(), d);
+    }
+
+    // Only `FREE_LIST_SIZES` sizes are kept.
+    let blocks: Vec<_> = (1..=FREE_LIST_SIZES + 1)
+        .map(|i| {
+            let layout = Layout::from_size_align(8 * (i + 8), 8).unwrap();
+            (alloc.alloc(layout).unwrap(), layout)
+        })
+        .collect();
+    for &(block, layout) in &blocks {
+        alloc.dealloc(block, layout);
+    }
+    let (last, layout) = blocks[FREE_LIST_SIZES];
+    assert_ne!(alloc.alloc(layout).unwrap(), last);
+
+    alloc.set_free_list(false);
+    assert_ne!(alloc.alloc(small).unwrap(), d);
+    alloc.dealloc(keep, small);
+}
+
+#[test]
+fn page_addr_round_trips() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+
+    let addr = alloc.alloc_page_addr(1, PAGE_SIZE).unwrap();
+    assert_eq!(addr.as_usize(), start + 3 * PAGE_SIZE);
+    assert_eq!(PageAddr::new(addr.as_usize()), addr);
+    let next = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert!(PageAddr::new(next) < addr);
+    assert_eq!(alloc.used_pages(), 2);
+
+    alloc.dealloc_page_addr(addr, 1).unwrap();
+    alloc.dealloc_page_addr(PageAddr::new(next), 1).unwrap();
+    assert_eq!(alloc.used_pages(), 0);
+    assert!(matches!(
+        alloc.dealloc_page_addr(addr, 1),
+        Err(AllocError::NotAllocated)
+    ));
+}
+
+#[cfg(feature = "page-blocks")]
+#[test]
+fn allocated_pages_lists_live_blocks() {
+    use crate::MAX_PAGE_BLOCKS;
+
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, ARENA_PAGES * PAGE_SIZE);
+    assert_eq!(alloc.allocated_pages().count(), 0);
+
+    let a = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    let b = alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    let front = alloc.alloc_pages_front(1, PAGE_SIZE).unwrap();
+    let c = alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    let blocks: Vec<_> = alloc.allocated_pages().collect();
+    assert_eq!(blocks, [(a, 1), (b, 2), (front, 1), (c, 3)]);
+    assert_eq!(b, a - 2 * PAGE_SIZE);
+
+    alloc.dealloc_pages(b, 2);
+    let blocks: Vec<_> = alloc.allocated_pages().collect();
+    assert_eq!(blocks, [(a, 1), (front, 1), (c, 3)]);
+
+    alloc.reset();
+    assert_eq!(alloc.allocated_pages().count(), 0);
+    for _ in 0..=MAX_PAGE_BLOCKS {
+        alloc.alloc_pages(0, PAGE_SIZE).unwrap();
+    }
+    assert_eq!(alloc.allocated_pages().count(), MAX_PAGE_BLOCKS);
+}
+
+#[test]
+fn scratch_lends_the_free_gap() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    assert!(alloc.scratch().is_empty());
+    alloc.init(start, 4 * PAGE_SIZE);
+    let layout = Layout::from_size_align(16, 8).unwrap();
+    let a = alloc.alloc(layout).unwrap();
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+
+    let scratch = alloc.scratch();
+    assert_eq!(scratch.as_ptr() as usize, start + 16);
+    assert_eq!(scratch.len(), 3 * PAGE_SIZE - 16);
+    scratch[..4].copy_from_slice(b"\xd0\x0d\xfe\xed");
+    assert_eq!(alloc.used_bytes(), 16);
+
+    let b = alloc.alloc(layout).unwrap();
+    assert_eq!(b.as_ptr() as usize, start + 16);
+    assert_eq!(unsafe { *b.as_ptr() }, 0xd0);
+    alloc.dealloc(b, layout);
+    alloc.dealloc(a, layout);
+
+    // Reserved memory is never lent.
+    alloc.reserve(start + PAGE_SIZE, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.scratch().len(), PAGE_SIZE);
+}
+
+#[test]
+fn owns_checks_used_ranges() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 2>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    alloc
+        .add_memory(start + 8 * PAGE_SIZE, 4 * PAGE_SIZE)
+        .unwrap();
+    let at = |addr: usize| NonNull::new(addr as *mut u8).unwrap();
+    assert!(!alloc.owns(at(start)));
+
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let bytes = alloc.alloc(layout).unwrap();
+    let pages = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert!(alloc.owns(bytes));
+    assert!(alloc.owns(at(bytes.as_ptr() as usize + 63)));
+    assert!(!alloc.owns(at(bytes.as_ptr() as usize + 64)));
+    assert!(alloc.owns(at(pages)));
+    assert!(alloc.owns(at(start + 4 * PAGE_SIZE - 1)));
+    assert!(!alloc.owns(at(pages - 1)));
+    assert!(!alloc.owns(at(start + 4 * PAGE_SIZE)));
+
+    // The extra region is owned once it is used.
+    assert!(!alloc.owns(at(start + 11 * PAGE_SIZE)));
+    let extra = alloc.alloc_pages(3, PAGE_SIZE).unwrap();
+    assert_eq!(extra, start + 9 * PAGE_SIZE);
+    assert!(alloc.owns(at(start + 11 * PAGE_SIZE)));
+    assert!(!alloc.owns(at(start + 8 * PAGE_SIZE)));
+
+    // A reserved span is not owned, even once skipped over.
+    alloc.reserve(start + PAGE_SIZE, 64).unwrap();
+    let layout = Layout::from_size_align(PAGE_SIZE, 8).unwrap();
+    let below = alloc.alloc(layout).unwrap().as_ptr() as usize;
+    assert_eq!(below, start + PAGE_SIZE + 64);
+    assert!(alloc.owns(at(start + PAGE_SIZE - 1)));
+    assert!(!alloc.owns(at(start + PAGE_SIZE)));
+    assert!(!alloc.owns(at(start + PAGE_SIZE + 63)));
+    assert!(alloc.owns(at(below)));
+
+    let foreign = Box::new(0u64);
+    assert!(!alloc.owns(NonNull::from(&*foreign).cast()));
+}
+
+#[test]
+fn accounting_adds_up_when_nearly_full() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    let total = alloc.total_bytes();
+
+    // Odd sizes and alignments leave padding up to the very end.
+    let mut i = 0;
+    loop {
+        let layout = Layout::from_size_align(1 + i % 97, 1 << (i % 7)).unwrap();
+        if alloc.alloc(layout).is_err() {
+            break;
+        }
+        assert_eq!(alloc.used_bytes() + alloc.available_bytes(), total);
+        i += 1;
+    }
+    assert!(alloc.available_bytes() < 97 + 64);
+    assert!(alloc.used_bytes() <= total);
+    let last = alloc.available_bytes();
+    if last > 0 {
+        let layout = Layout::from_size_align(last, 1).unwrap();
+        alloc.alloc(layout).unwrap();
+    }
+    assert_eq!(alloc.used_bytes(), total);
+    assert_eq!(alloc.available_bytes(), 0);
+    assert!(alloc.alloc(Layout::new::<u8>()).is_err());
+
+    // Pages, front pages and reservations in the gap are in neither count.
+    alloc.reset();
+    alloc.reserve(start + PAGE_SIZE, 16).unwrap();
+    alloc.alloc_pages_front(1, PAGE_SIZE).unwrap();
+    alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    assert_eq!(alloc.available_bytes(), 2 * PAGE_SIZE - 16);
+    // Bumping past the reservation counts it as used.
+    let layout = Layout::from_size_align(100, 8).unwrap();
+    alloc.alloc(layout).unwrap();
+    assert_eq!(alloc.used_bytes(), 16 + 100);
+    assert_eq!(alloc.available_bytes(), 2 * PAGE_SIZE - 16 - 100);
+}
+
+#[test]
+fn max_regions_sizes_the_extra_regions() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE, 4>::new();
+    alloc.init(start, PAGE_SIZE);
+    for i in 1..=3 {
+        alloc
+            .add_memory(start + 2 * i * PAGE_SIZE, PAGE_SIZE)
+            .unwrap();
+    }
+    assert_eq!(alloc.total_bytes(), 4 * PAGE_SIZE);
+
+    let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+    let blocks: Vec<_> = (0..4)
+        .map(|_| alloc.alloc(layout).unwrap().as_ptr() as usize)
+        .collect();
+    assert_eq!(
+        blocks,
+        [
+            start,
+            start + 2 * PAGE_SIZE,
+            start + 4 * PAGE_SIZE,
+            start + 6 * PAGE_SIZE
+        ]
+    );
+    assert!(alloc.alloc(layout).is_err());
+    // The primary region counts, so a fourth one is too many.
+    let res = alloc.add_memory(start + 8 * PAGE_SIZE, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::NoMemory)));
+    assert_eq!(alloc.total_bytes(), 4 * PAGE_SIZE);
+
+    // By default, only contiguous memory can be added, and no space is
+    // taken for extra regions.
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, PAGE_SIZE);
+    alloc.add_memory(start + PAGE_SIZE, PAGE_SIZE).unwrap();
+    let res = alloc.add_memory(start + 4 * PAGE_SIZE, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::NoMemory)));
+    assert_eq!(alloc.total_bytes(), 2 * PAGE_SIZE);
+    assert_eq!(alloc.checkpoint().byte_next.len(), 1);
+    assert_eq!(
+        core::mem::size_of::<EarlyAllocator<PAGE_SIZE>>() + core::mem::size_of::<Region>(),
+        core::mem::size_of::<EarlyAllocator<PAGE_SIZE, 2>>()
+    );
+
+    // The locked wrapper passes `MAX_REGIONS` on.
+    let locked = LockedEarlyAllocator::<PAGE_SIZE>::new();
+    locked.init(start, PAGE_SIZE);
+    let res = locked.lock().add_memory(start + 4 * PAGE_SIZE, PAGE_SIZE);
+    assert!(matches!(res, Err(AllocError::NoMemory)));
+    let locked = LockedEarlyAllocator::<PAGE_SIZE, 2>::new();
+    locked.init(start, PAGE_SIZE);
+    locked
+        .lock()
+        .add_memory(start + 4 * PAGE_SIZE, PAGE_SIZE)
+        .unwrap();
+    assert_eq!(locked.lock().total_bytes(), 2 * PAGE_SIZE);
+}
+
+#[test]
+fn seal_hands_over_the_free_region() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    assert_eq!(alloc.free_region(), (start, start + 4 * PAGE_SIZE));
+
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let block = alloc.alloc(layout).unwrap();
+    let pages = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    let (free_start, free_end) = alloc.free_region();
+    assert_eq!((free_start, free_end), (start + 64, pages));
+    assert_eq!(free_end - free_start, alloc.available_bytes());
+
+    alloc.seal();
+    assert!(alloc.is_sealed());
+    assert!(matches!(alloc.alloc(layout), Err(AllocError::NoMemory)));
+    assert!(matches!(
+        alloc.alloc_pages(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    assert!(matches!(
+        alloc.alloc_pages_front(1, PAGE_SIZE),
+        Err(AllocError::NoMemory)
+    ));
+    let grown = Layout::from_size_align(128, 8).unwrap();
+    assert!(matches!(
+        alloc.realloc(block, layout, grown),
+        Err(AllocError::NoMemory)
+    ));
+    assert_eq!(alloc.free_region(), (free_start, free_end));
+
+    // Frees still work, but nothing more is handed out.
+    alloc.dealloc(block, layout);
+    alloc.dealloc_pages(pages, 1);
+    assert_eq!(alloc.stats().byte_count, 0);
+    alloc.reset();
+    assert!(alloc.alloc(layout).is_err());
+    alloc.init(start, 4 * PAGE_SIZE);
+    assert!(!alloc.is_sealed());
+    assert!(alloc.alloc(layout).is_ok());
+}
+
+#[test]
+fn grow_pages_extends_the_latest_block() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 8 * PAGE_SIZE);
+    let older = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    let ring = alloc.alloc_pages(2, PAGE_SIZE).unwrap();
+    unsafe { (ring as *mut u8).write(0x5a) };
+
+    let grown = alloc.grow_pages(ring, 2, 3).unwrap();
+    assert_eq!(grown, ring - 3 * PAGE_SIZE);
+    assert_eq!(unsafe { (ring as *const u8).read() }, 0x5a);
+    assert_eq!(alloc.used_pages(), 6);
+    assert_eq!(alloc.available_pages(), 2);
+    assert_eq!(alloc.stats().page_count, 2);
+    #[cfg(feature = "page-blocks")]
+    assert!(alloc.allocated_pages().eq([(older, 1), (grown, 5)]));
+
+    // Only the latest block can grow, and only into free pages.
+    assert!(matches!(
+        alloc.grow_pages(older, 1, 1),
+        Err(AllocError::InvalidParam)
+    ));
+    assert!(matches!(
+        alloc.grow_pages(ring, 2, 1),
+        Err(AllocError::InvalidParam)
+    ));
+    let layout = Layout::from_size_align(PAGE_SIZE + 1, 8).unwrap();
+    alloc.alloc(layout).unwrap();
+    assert!(matches!(
+        alloc.grow_pages(grown, 5, 1),
+        Err(AllocError::NoMemory)
+    ));
+    assert_eq!(alloc.used_pages(), 6);
+}
+
+#[test]
+fn grow_pages_needs_a_page_block() {
+    let arena = arena();
+    let start = base(&arena);
+    let mut alloc = EarlyAllocator::<PAGE_SIZE>::new();
+    alloc.init(start, 4 * PAGE_SIZE);
+    assert!(matches!(
+        alloc.grow_pages(start + 4 * PAGE_SIZE, 0, 1),
+        Err(AllocError::InvalidParam)
+    ));
+    let pages = alloc.alloc_pages(1, PAGE_SIZE).unwrap();
+    alloc.reserve(pages - PAGE_SIZE, PAGE_SIZE).unwrap();
+    assert!(matches!(
+        alloc.grow_pages(pages, 1, 1),
+        Err(AllocError::NoMemory)
+    ));
+    alloc.seal();
+    assert!(matches!(
+        alloc.grow_pages(pages, 1, 0),
+        Err(AllocError::NoMemory)
+    ));
+}
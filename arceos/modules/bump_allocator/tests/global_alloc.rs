@@ -0,0 +1,84 @@
+//! Routes every heap allocation of this test binary through a
+//! [`LockedEarlyAllocator`].
+
+use std::alloc::{GlobalAlloc, Layout};
+use std::ptr::addr_of;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use bump_allocator::LockedEarlyAllocator;
+
+const ARENA_SIZE: usize = 32 << 20;
+
+#[repr(C, align(4096))]
+struct Arena([u8; ARENA_SIZE]);
+
+static mut ARENA: Arena = Arena([0; ARENA_SIZE]);
+
+fn arena_range() -> core::ops::Range<usize> {
+    let start = unsafe { addr_of!(ARENA) } as usize;
+    start..start + ARENA_SIZE
+}
+
+/// Initializes the inner allocator on first use, since the test harness
+/// allocates before any test gets a chance to run.
+struct LazyEarlyAllocator {
+    inner: LockedEarlyAllocator<0x1000>,
+    state: AtomicU8,
+}
+
+const UNINIT: u8 = 0;
+const INITIALIZING: u8 = 1;
+const READY: u8 = 2;
+
+impl LazyEarlyAllocator {
+    fn get(&self) -> &LockedEarlyAllocator<0x1000> {
+        if self
+            .state
+            .compare_exchange(UNINIT, INITIALIZING, Ordering::Acquire, Ordering::Acquire)
+            .is_ok()
+        {
+            let range = arena_range();
+            self.inner.init(range.start, range.len());
+            self.state.store(READY, Ordering::Release);
+        }
+        while self.state.load(Ordering::Acquire) != READY {
+            core::hint::spin_loop();
+        }
+        &self.inner
+    }
+}
+
+unsafe impl GlobalAlloc for LazyEarlyAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.get().alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.get().dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: LazyEarlyAllocator = LazyEarlyAllocator {
+    inner: LockedEarlyAllocator::new(),
+    state: AtomicU8::new(UNINIT),
+};
+
+#[test]
+fn box_is_served_from_arena() {
+    let boxed = Box::new(0x1234_5678_u64);
+    let addr = &*boxed as *const u64 as usize;
+    assert!(arena_range().contains(&addr));
+    assert_eq!(addr % core::mem::align_of::<u64>(), 0);
+    assert_eq!(*boxed, 0x1234_5678);
+}
+
+#[test]
+fn vec_is_served_from_arena() {
+    let mut v = Vec::new();
+    for i in 0..10_000u32 {
+        v.push(i);
+    }
+    assert!(arena_range().contains(&(v.as_ptr() as usize)));
+    assert!(v.iter().copied().eq(0..10_000));
+}
@@ -1,8 +1,38 @@
+//! A minimal guest kernel, which issues one SBI call and stops.
+//!
+//! By default it runs the sequence `simple_hv` expects: it reads `mhartid`
+//! into `a1` and the argument at offset 64 into `a0`, both of which trap to
+//! the hypervisor, then shuts down with the legacy SBI call 8.
+//!
+//! With the `param` feature, the loader picks the call instead, by filling
+//! in the guest memory below, all values being little-endian `u64`s:
+//!
+//! | Offset | Contents                          |
+//! |--------|-----------------------------------|
+//! | 64     | The argument, passed in `a0`      |
+//! | 72     | The call number, passed in `a7`   |
+//! | 80     | Written with the `a0` returned    |
+//!
+//! Once the result is stored, the payload idles with `wfi` forever, so the
+//! host must map that page writable and read the result back from it.
+//!
+//! With the `script` feature, it issues a sequence of calls instead, read
+//! from a table at offset 256. Each entry is a call number followed by its
+//! argument, 16 bytes in all, and an entry with call number `u64::MAX` ends
+//! the table, so that call 0 can be scripted too. Each call gets its
+//! argument in `a0` and number in `a7`; other registers hold no meaning.
+//! The payload then shuts down with the legacy SBI call 8, passing the
+//! argument at offset 64 in `a0`.
+
 #![no_std]
 #![no_main]
 
 use core::panic::PanicInfo;
 
+#[cfg(all(feature = "param", feature = "script"))]
+compile_error!("the `param` and `script` features are exclusive");
+
+#[cfg(not(any(feature = "param", feature = "script")))]
 #[no_mangle]
 unsafe extern "C" fn _start() -> ! {
     core::arch::asm!(
@@ -14,6 +44,47 @@ unsafe extern "C" fn _start() -> ! {
     )
 }
 
+#[cfg(feature = "param")]
+#[no_mangle]
+unsafe extern "C" fn _start() -> ! {
+    core::arch::asm!(
+        "ld a0, 64(zero)",
+        "ld a7, 72(zero)",
+        "ecall",
+        "sd a0, 80(zero)",
+        out("a0") _,
+        out("a1") _,
+        out("a7") _,
+    );
+    loop {
+        core::arch::asm!("wfi");
+    }
+}
+
+#[cfg(feature = "script")]
+#[no_mangle]
+unsafe extern "C" fn _start() -> ! {
+    // `t0` walks the table and `t1` holds the sentinel. SBI calls only
+    // clobber `a0` and `a1`.
+    core::arch::asm!(
+        "csrr a1, mhartid",
+        "li t0, 256",
+        "li t1, -1",
+        "1:",
+        "ld a7, 0(t0)",
+        "beq a7, t1, 2f",
+        "ld a0, 8(t0)",
+        "ecall",
+        "addi t0, t0, 16",
+        "j 1b",
+        "2:",
+        "ld a0, 64(zero)",
+        "li a7, 8",
+        "ecall",
+        options(noreturn)
+    )
+}
+
 #[panic_handler]
 fn panic(_info: &PanicInfo) -> ! {
     loop {}
@@ -0,0 +1,249 @@
+//! A fixed-capacity hash map, stored inline without any heap allocation.
+
+use core::borrow::Borrow;
+use core::fmt;
+
+use super::hash::{BuildDefaultHasher, BuildHasher, Hash, Hasher};
+use super::probe::{ideal_bucket, ProbeSeq};
+
+/// A hash map of at most `N` entries, stored in an array of `N` buckets, so
+/// that it can be used before the heap is up, e.g. for a small interrupt
+/// routing table.
+///
+/// Keys are placed with the same quadratic probing as [`HashMap`], which
+/// requires `N` to be a power of two; other capacities fail to compile.
+/// The map never grows: once all `N` buckets are taken, inserting a new key
+/// fails with a [`FullError`].
+///
+/// Lookups of absent keys in a nearly full map probe most buckets, and
+/// removals move the remaining entries back along their probe sequences,
+/// so the map is meant for a handful of entries.
+///
+/// [`HashMap`]: super::HashMap
+#[derive(Clone)]
+pub struct ArrayHashMap<K, V, const N: usize, S = BuildDefaultHasher> {
+    buckets: [Option<(K, V)>; N],
+    len: usize,
+    build_hasher: S,
+}
+
+impl<K, V, const N: usize> ArrayHashMap<K, V, N>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Self::with_hasher(BuildDefaultHasher::default())
+    }
+}
+
+impl<K, V, const N: usize> Default for ArrayHashMap<K, V, N>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, const N: usize, S> ArrayHashMap<K, V, N, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    const CAPACITY_IS_POWER_OF_TWO: () =
+        assert!(N.is_power_of_two(), "capacity is not a power of two");
+
+    /// Creates an empty map which hashes keys with hashers built by
+    /// `hasher`.
+    pub fn with_hasher(hasher: S) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CAPACITY_IS_POWER_OF_TWO;
+        Self {
+            buckets: core::array::from_fn(|_| None),
+            len: 0,
+            build_hasher: hasher,
+        }
+    }
+
+    /// Returns the ideal bucket of `k`, see `HashMap::hash`.
+    fn hash<Q: Hash + ?Sized>(&self, k: &Q) -> usize {
+        let mut hasher = self.build_hasher.build_hasher();
+        k.hash(&mut hasher);
+        ideal_bucket(hasher.finish(), N)
+    }
+
+    /// Returns `Ok` with the bucket holding `k`, or `Err` with the first
+    /// empty bucket of its probe sequence, if any.
+    fn probe<Q>(&self, k: &Q) -> Result<usize, Option<usize>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        for index in ProbeSeq::new(self.hash(k), N) {
+            match &self.buckets[index] {
+                None => return Err(Some(index)),
+                Some((key, _)) if key.borrow() == k => return Ok(index),
+                Some(_) => {}
+            }
+        }
+        Err(None)
+    }
+
+    /// Inserts a key-value pair into the map, returning the previous value
+    /// of the key if it was present.
+    ///
+    /// Replacing the value of a key always succeeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns the pair back in a [`FullError`] if the key is new and all
+    /// `N` buckets are taken.
+    pub fn insert(&mut self, k: K, v: V) -> Result<Option<V>, FullError<K, V>> {
+        match self.probe(&k) {
+            Ok(index) => {
+                let (_, value) = self.buckets[index].as_mut().unwrap();
+                Ok(Some(core::mem::replace(value, v)))
+            }
+            Err(Some(index)) => {
+                self.buckets[index] = Some((k, v));
+                self.len += 1;
+                Ok(None)
+            }
+            Err(None) => Err(FullError { key: k, value: v }),
+        }
+    }
+
+    /// Returns a reference to the value of the key.
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.probe(k).ok()?;
+        self.buckets[index].as_ref().map(|(_, v)| v)
+    }
+
+    /// Returns a mutable reference to the value of the key.
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.probe(k).ok()?;
+        self.buckets[index].as_mut().map(|(_, v)| v)
+    }
+
+    /// Returns `true` if the map contains the key.
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.probe(k).is_ok()
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.probe(k).ok()?;
+        let (_, v) = self.buckets[index].take()?;
+        self.len -= 1;
+        self.settle();
+        Some(v)
+    }
+
+    /// Moves entries back into the buckets emptied by a removal, until each
+    /// one sits in the first empty bucket of its probe sequence, so that
+    /// lookups can stop at the first empty bucket.
+    ///
+    /// Every move brings an entry closer to its ideal bucket, so this ends.
+    fn settle(&mut self) {
+        loop {
+            let mut moved = false;
+            for index in 0..N {
+                let Some((key, _)) = &self.buckets[index] else {
+                    continue;
+                };
+                let target = ProbeSeq::new(self.hash(key), N)
+                    .find(|&i| i == index || self.buckets[i].is_none())
+                    .unwrap();
+                if target != index {
+                    self.buckets.swap(index, target);
+                    moved = true;
+                }
+            }
+            if !moved {
+                return;
+            }
+        }
+    }
+}
+
+impl<K, V, const N: usize, S> ArrayHashMap<K, V, N, S> {
+    /// Returns the number of buckets, which is the most entries the map can
+    /// hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns `true` if no new key can be inserted.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Returns an iterator over all entries, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.buckets.iter().flatten().map(|(k, v)| (k, v))
+    }
+
+    /// Returns an iterator over all entries, with mutable references to the
+    /// values, in arbitrary order.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&K, &mut V)> {
+        self.buckets.iter_mut().flatten().map(|(k, v)| (&*k, v))
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.buckets.iter_mut().for_each(|bucket| *bucket = None);
+        self.len = 0;
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, const N: usize, S> fmt::Debug for ArrayHashMap<K, V, N, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// The error returned by [`ArrayHashMap::insert`] when the map is full.
+#[derive(Debug)]
+pub struct FullError<K, V> {
+    /// The key that was not inserted.
+    pub key: K,
+    /// The value that was not inserted.
+    pub value: V,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> fmt::Display for FullError<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert key {:?} with value {:?}, the map is full",
+            self.key, self.value,
+        )
+    }
+}
@@ -0,0 +1,142 @@
+//! A Bloom filter over the [`hash`](super::hash) primitives.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::hash::{BuildDefaultHasher, BuildHasher, Hash, Hasher};
+
+const LN_2: f64 = core::f64::consts::LN_2;
+
+/// A probabilistic set, answering whether an item is definitely absent or
+/// possibly present.
+///
+/// Items are never stored, only `k` bits per item are set in a fixed bit
+/// array. The `k` positions come from a single hash of the item split in two
+/// halves `h1` and `h2`, as `h1 + i * h2` (Kirsch-Mitzenmacher double
+/// hashing).
+#[derive(Clone)]
+pub struct BloomFilter<S = BuildDefaultHasher> {
+    bits: Vec<u64>,
+    num_bits: u64,
+    num_hashes: u32,
+    build_hasher: S,
+}
+
+impl BloomFilter {
+    /// Creates an empty filter sized to hold `expected_items` items with
+    /// about `false_positive_rate` false positives.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `false_positive_rate` is not strictly between 0 and 1.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        Self::with_hasher(
+            expected_items,
+            false_positive_rate,
+            BuildDefaultHasher::default(),
+        )
+    }
+}
+
+impl<S: BuildHasher> BloomFilter<S> {
+    /// Creates an empty filter like [`BloomFilter::new`], which hashes items
+    /// with hashers built by `hasher`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `false_positive_rate` is not strictly between 0 and 1.
+    pub fn with_hasher(expected_items: usize, false_positive_rate: f64, hasher: S) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be between 0 and 1",
+        );
+        // m = -n ln(p) / ln(2)^2 bits and k = m / n ln(2) hashes minimize the
+        // false positive rate. m is rounded up and k to the nearest integer.
+        let n = expected_items.max(1) as f64;
+        let num_bits = ceil(-n * ln(false_positive_rate) / (LN_2 * LN_2)).max(64);
+        let num_hashes = round(num_bits as f64 / n * LN_2).clamp(1, 32) as u32;
+        Self {
+            bits: vec![0; num_bits.div_ceil(64) as usize],
+            num_bits,
+            num_hashes,
+            build_hasher: hasher,
+        }
+    }
+
+    /// Adds `item` to the filter.
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        for bit in self.positions(item) {
+            self.bits[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    /// Returns `false` if `item` was never inserted. A `true` may be a false
+    /// positive.
+    pub fn contains<T: Hash + ?Sized>(&self, item: &T) -> bool {
+        self.positions(item)
+            .all(|bit| self.bits[(bit / 64) as usize] & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns the bits to set or test for `item`.
+    fn positions<T: Hash + ?Sized>(&self, item: &T) -> impl Iterator<Item = u64> {
+        let mut hasher = self.build_hasher.build_hasher();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+        // A zero step would set the same bit `k` times.
+        let (h1, h2) = (hash & 0xffff_ffff, (hash >> 32) | 1);
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+impl<S> BloomFilter<S> {
+    /// Returns the size of the bit array.
+    pub fn num_bits(&self) -> u64 {
+        self.num_bits
+    }
+
+    /// Returns the number of bits set per item.
+    pub fn num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+
+    /// Removes all items.
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+    }
+}
+
+/// Returns the natural logarithm of a positive, finite `x`.
+///
+/// `core` has no `f64::ln`. With `x = m * 2^e` and `m` in `[1, 2)`,
+/// `ln(m) = 2 atanh(z)` for `z = (m - 1) / (m + 1) < 1/3`, whose series
+/// converges quickly.
+fn ln(x: f64) -> f64 {
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+    let z = (m - 1.0) / (m + 1.0);
+    let z2 = z * z;
+    let (mut term, mut sum) = (z, 0.0);
+    for i in 0..12 {
+        sum += term / (2 * i + 1) as f64;
+        term *= z2;
+    }
+    exponent as f64 * LN_2 + 2.0 * sum
+}
+
+/// Rounds a non-negative `x` up to an integer.
+fn ceil(x: f64) -> u64 {
+    let n = x as u64;
+    if (n as f64) < x {
+        n + 1
+    } else {
+        n
+    }
+}
+
+/// Rounds a non-negative `x` to the nearest integer, halves away from zero.
+fn round(x: f64) -> u64 {
+    // Truncating a non-negative number rounds it down.
+    (x + 0.5) as u64
+}
@@ -0,0 +1,717 @@
+//! Generic hashing support, modeled on [`core::hash`].
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+/// A trait for hashing an arbitrary stream of bytes.
+pub trait Hasher {
+    /// Returns the hash value for the values written so far.
+    fn finish(&self) -> u64;
+
+    /// Writes some data into this `Hasher`.
+    fn write(&mut self, bytes: &[u8]);
+
+    /// Writes a single `u8` into this hasher.
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i])
+    }
+
+    /// Writes a single `u16` into this hasher.
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_ne_bytes())
+    }
+
+    /// Writes a single `u32` into this hasher.
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_ne_bytes())
+    }
+
+    /// Writes a single `u64` into this hasher.
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_ne_bytes())
+    }
+
+    /// Writes a single `u128` into this hasher.
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_ne_bytes())
+    }
+
+    /// Writes a single `usize` into this hasher.
+    fn write_usize(&mut self, i: usize) {
+        self.write(&i.to_ne_bytes())
+    }
+
+    /// Writes a single `i8` into this hasher.
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8(i as u8)
+    }
+
+    /// Writes a single `i16` into this hasher.
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16)
+    }
+
+    /// Writes a single `i32` into this hasher.
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32)
+    }
+
+    /// Writes a single `i64` into this hasher.
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64)
+    }
+
+    /// Writes a single `i128` into this hasher.
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128)
+    }
+
+    /// Writes a single `isize` into this hasher.
+    fn write_isize(&mut self, i: isize) {
+        self.write_usize(i as usize)
+    }
+
+    /// Writes a single `f32` into this hasher, as its bit pattern.
+    ///
+    /// This hashes bits, not numeric values: `0.0` and `-0.0` hash
+    /// differently, and so do NaNs with different payloads, although neither
+    /// pair agrees with `==` anyway.
+    fn write_f32(&mut self, f: f32) {
+        self.write_u32(f.to_bits())
+    }
+
+    /// Writes a single `f64` into this hasher, as its bit pattern.
+    ///
+    /// See [`Hasher::write_f32`] for the caveats.
+    fn write_f64(&mut self, f: f64) {
+        self.write_u64(f.to_bits())
+    }
+
+    /// Writes a length prefix, as done before the elements of a collection.
+    fn write_length_prefix(&mut self, len: usize) {
+        self.write_usize(len);
+    }
+
+    /// Writes a single `str` into this hasher, followed by a `0xff`
+    /// terminator so that adjacent strings don't run into each other.
+    fn write_str(&mut self, s: &str) {
+        self.write(s.as_bytes());
+        self.write_u8(0xff);
+    }
+
+    /// Resets this hasher to its initial state, so it can be reused for
+    /// another value.
+    fn reset(&mut self)
+    where
+        Self: Default + Sized,
+    {
+        *self = Self::default();
+    }
+}
+
+impl<H: Hasher + ?Sized> Hasher for &mut H {
+    fn finish(&self) -> u64 {
+        (**self).finish()
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        (**self).write(bytes)
+    }
+    fn write_u8(&mut self, i: u8) {
+        (**self).write_u8(i)
+    }
+    fn write_u16(&mut self, i: u16) {
+        (**self).write_u16(i)
+    }
+    fn write_u32(&mut self, i: u32) {
+        (**self).write_u32(i)
+    }
+    fn write_u64(&mut self, i: u64) {
+        (**self).write_u64(i)
+    }
+    fn write_u128(&mut self, i: u128) {
+        (**self).write_u128(i)
+    }
+    fn write_usize(&mut self, i: usize) {
+        (**self).write_usize(i)
+    }
+    fn write_i8(&mut self, i: i8) {
+        (**self).write_i8(i)
+    }
+    fn write_i16(&mut self, i: i16) {
+        (**self).write_i16(i)
+    }
+    fn write_i32(&mut self, i: i32) {
+        (**self).write_i32(i)
+    }
+    fn write_i64(&mut self, i: i64) {
+        (**self).write_i64(i)
+    }
+    fn write_i128(&mut self, i: i128) {
+        (**self).write_i128(i)
+    }
+    fn write_isize(&mut self, i: isize) {
+        (**self).write_isize(i)
+    }
+    fn write_f32(&mut self, f: f32) {
+        (**self).write_f32(f)
+    }
+    fn write_f64(&mut self, f: f64) {
+        (**self).write_f64(f)
+    }
+    fn write_length_prefix(&mut self, len: usize) {
+        (**self).write_length_prefix(len)
+    }
+    fn write_str(&mut self, s: &str) {
+        (**self).write_str(s)
+    }
+}
+
+/// A hashable type.
+pub trait Hash {
+    /// Feeds this value into the given [`Hasher`].
+    fn hash<H: Hasher>(&self, state: &mut H);
+}
+
+/// Strings go through [`Hasher::write_str`], whose terminator keeps
+/// `("ab", "c")` and `("a", "bc")` apart.
+impl Hash for str {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_str(self);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Hash for String {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+macro_rules! impl_hash_int {
+    ($($ty:ty => $write:ident),* $(,)?) => {
+        $(
+            impl Hash for $ty {
+                fn hash<H: Hasher>(&self, state: &mut H) {
+                    state.$write(*self);
+                }
+            }
+        )*
+    };
+}
+
+impl_hash_int! {
+    u8 => write_u8,
+    u16 => write_u16,
+    u32 => write_u32,
+    u64 => write_u64,
+    u128 => write_u128,
+    usize => write_usize,
+    i8 => write_i8,
+    i16 => write_i16,
+    i32 => write_i32,
+    i64 => write_i64,
+    i128 => write_i128,
+    isize => write_isize,
+}
+
+impl Hash for bool {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u8(*self as u8);
+    }
+}
+
+impl Hash for char {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u32(*self as u32);
+    }
+}
+
+impl<T: Hash> Hash for [T] {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_length_prefix(self.len());
+        for item in self {
+            item.hash(state);
+        }
+    }
+}
+
+impl<T: Hash, const N: usize> Hash for [T; N] {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Hash> Hash for Vec<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+impl<T: Hash + ?Sized> Hash for &T {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl<T: Hash> Hash for Option<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            None => state.write_u8(0),
+            Some(value) => {
+                state.write_u8(1);
+                value.hash(state);
+            }
+        }
+    }
+}
+
+macro_rules! impl_hash_tuple {
+    ($($name:ident)+) => {
+        impl<$($name: Hash),+> Hash for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn hash<S: Hasher>(&self, state: &mut S) {
+                let ($($name,)+) = self;
+                $($name.hash(state);)+
+            }
+        }
+    };
+}
+
+impl_hash_tuple! { T1 }
+impl_hash_tuple! { T1 T2 }
+impl_hash_tuple! { T1 T2 T3 }
+impl_hash_tuple! { T1 T2 T3 T4 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 T7 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 T7 T8 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 T7 T8 T9 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 }
+impl_hash_tuple! { T1 T2 T3 T4 T5 T6 T7 T8 T9 T10 T11 T12 }
+
+/// A trait for creating instances of [`Hasher`].
+///
+/// Each key of a map is hashed by a fresh hasher, so that the hashes only
+/// depend on the keys.
+pub trait BuildHasher {
+    /// The type of the hashers created.
+    type Hasher: Hasher;
+
+    /// Creates a new hasher.
+    fn build_hasher(&self) -> Self::Hasher;
+
+    /// Calculates the hash of a single value.
+    fn hash_one<T: Hash>(&self, x: T) -> u64 {
+        let mut hasher = self.build_hasher();
+        x.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// The standard initial state of [`FNV1aHasher`].
+pub const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The 64-bit [FNV-1a] hasher.
+///
+/// Fast and simple, but not resistant to crafted collisions.
+///
+/// [FNV-1a]: https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function
+#[derive(Clone, Copy, Debug)]
+pub struct FNV1aHasher {
+    hash: u64,
+}
+
+impl Default for FNV1aHasher {
+    fn default() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+}
+
+impl FNV1aHasher {
+    /// Creates a hasher starting from `seed` instead of the standard offset
+    /// basis.
+    ///
+    /// Hashers with different seeds give independent hashes of the same
+    /// input. `with_seed(`[`FNV_OFFSET_BASIS`]`)` is the default hasher.
+    pub const fn with_seed(seed: u64) -> Self {
+        Self { hash: seed }
+    }
+
+    /// Discards the hashed data, starting over from `seed`.
+    pub fn reset_with(&mut self, seed: u64) {
+        self.hash = seed;
+    }
+}
+
+impl Hasher for FNV1aHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash ^= byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+/// A [`BuildHasher`] creating default [`FNV1aHasher`]s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildFNV1a;
+
+impl BuildHasher for BuildFNV1a {
+    type Hasher = FNV1aHasher;
+
+    fn build_hasher(&self) -> FNV1aHasher {
+        FNV1aHasher::default()
+    }
+}
+
+/// The hasher used by maps that don't pick one.
+///
+/// [`SipHasher13`] is the better choice for untrusted keys, but it needs a
+/// random seed, which isn't available everywhere in the kernel.
+pub type DefaultHasher = FNV1aHasher;
+
+/// The [`BuildHasher`] used by [`HashMap`](super::HashMap) unless another
+/// one is given.
+pub type BuildDefaultHasher = BuildFNV1a;
+
+/// Creates a new [`DefaultHasher`].
+pub fn default_hasher() -> DefaultHasher {
+    DefaultHasher::default()
+}
+
+/// The [SipHash-1-3] hasher, keyed by two `u64`s.
+///
+/// Unlike [`FNV1aHasher`], collisions can't be crafted without knowing the
+/// keys, so this is the hasher to use for keys controlled by an attacker.
+/// There is no OS to seed it, so the keys must be provided by the caller.
+///
+/// [SipHash-1-3]: https://www.aumasson.jp/siphash/siphash.pdf
+#[derive(Clone, Copy, Debug)]
+pub struct SipHasher13 {
+    k0: u64,
+    k1: u64,
+    v0: u64,
+    v1: u64,
+    v2: u64,
+    v3: u64,
+    /// Unprocessed trailing bytes, little-endian.
+    tail: u64,
+    ntail: usize,
+    length: usize,
+}
+
+impl SipHasher13 {
+    /// Creates a `SipHasher13` keyed by `k0` and `k1`.
+    pub const fn new_with_keys(k0: u64, k1: u64) -> Self {
+        Self {
+            k0,
+            k1,
+            v0: k0 ^ 0x736f_6d65_7073_6575,
+            v1: k1 ^ 0x646f_7261_6e64_6f6d,
+            v2: k0 ^ 0x6c79_6765_6e65_7261,
+            v3: k1 ^ 0x7465_6462_7974_6573,
+            tail: 0,
+            ntail: 0,
+            length: 0,
+        }
+    }
+
+    /// Resets this hasher to its initial state, keeping the keys.
+    pub fn reset(&mut self) {
+        *self = Self::new_with_keys(self.k0, self.k1);
+    }
+
+    fn round(&mut self) {
+        self.v0 = self.v0.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(13) ^ self.v0;
+        self.v0 = self.v0.rotate_left(32);
+        self.v2 = self.v2.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(16) ^ self.v2;
+        self.v0 = self.v0.wrapping_add(self.v3);
+        self.v3 = self.v3.rotate_left(21) ^ self.v0;
+        self.v2 = self.v2.wrapping_add(self.v1);
+        self.v1 = self.v1.rotate_left(17) ^ self.v2;
+        self.v2 = self.v2.rotate_left(32);
+    }
+
+    fn compress(&mut self, m: u64) {
+        self.v3 ^= m;
+        self.round();
+        self.v0 ^= m;
+    }
+}
+
+impl Hasher for SipHasher13 {
+    fn finish(&self) -> u64 {
+        let mut state = *self;
+        let b = ((self.length as u64 & 0xff) << 56) | self.tail;
+        state.compress(b);
+        state.v2 ^= 0xff;
+        for _ in 0..3 {
+            state.round();
+        }
+        state.v0 ^ state.v1 ^ state.v2 ^ state.v3
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len());
+        for &byte in bytes {
+            self.tail |= (byte as u64) << (8 * self.ntail);
+            self.ntail += 1;
+            if self.ntail == 8 {
+                self.compress(self.tail);
+                self.tail = 0;
+                self.ntail = 0;
+            }
+        }
+    }
+}
+
+/// A [`BuildHasher`] creating [`SipHasher13`]s with fixed keys.
+///
+/// This plays the role of `RandomState` in `std`: pick the keys randomly
+/// once, e.g. from a hardware RNG or a boot-time timer, and share the state
+/// between maps.
+#[derive(Clone, Copy, Debug)]
+pub struct BuildSipHasher13 {
+    k0: u64,
+    k1: u64,
+}
+
+impl BuildSipHasher13 {
+    /// Creates a builder for hashers keyed by `k0` and `k1`.
+    pub const fn new(k0: u64, k1: u64) -> Self {
+        Self { k0, k1 }
+    }
+}
+
+impl BuildHasher for BuildSipHasher13 {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.k0, self.k1)
+    }
+}
+
+#[cfg(target_pointer_width = "64")]
+const FX_SEED: usize = 0x517c_c1b7_2722_0a95;
+#[cfg(target_pointer_width = "32")]
+const FX_SEED: usize = 0x9e37_79b9;
+
+/// The multiply-rotate hasher used by `rustc`.
+///
+/// Consumes a whole `usize` per step, which makes it much faster than
+/// [`FNV1aHasher`] on long keys. Not resistant to crafted collisions either.
+///
+/// Bytes that don't fill a word are held back until the next write, so
+/// splitting the input over several `write` calls doesn't change the hash.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FxHasher {
+    hash: usize,
+    /// Bytes written but not hashed yet, fewer than a word.
+    tail: [u8; FX_WORD],
+    ntail: usize,
+}
+
+const FX_WORD: usize = core::mem::size_of::<usize>();
+
+impl FxHasher {
+    #[inline]
+    fn add_to_hash(&mut self, i: usize) {
+        self.hash = (self.hash.rotate_left(5) ^ i).wrapping_mul(FX_SEED);
+    }
+
+    /// Hashes the bytes held back, as the largest integers they fill.
+    fn flush(&mut self) {
+        let tail = self.tail;
+        let mut rest = &tail[..self.ntail];
+        self.ntail = 0;
+        if FX_WORD > 4 && rest.len() >= 4 {
+            self.add_to_hash(u32::from_ne_bytes(rest[..4].try_into().unwrap()) as usize);
+            rest = &rest[4..];
+        }
+        if rest.len() >= 2 {
+            self.add_to_hash(u16::from_ne_bytes(rest[..2].try_into().unwrap()) as usize);
+            rest = &rest[2..];
+        }
+        if let Some(&byte) = rest.first() {
+            self.add_to_hash(byte as usize);
+        }
+    }
+
+    /// Hashes an integer written directly, after the bytes held back.
+    #[inline]
+    fn add_int(&mut self, i: usize) {
+        if self.ntail > 0 {
+            self.flush();
+        }
+        self.add_to_hash(i);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn finish(&self) -> u64 {
+        let mut state = *self;
+        state.flush();
+        state.hash as u64
+    }
+
+    fn write(&mut self, mut bytes: &[u8]) {
+        if self.ntail > 0 {
+            let fill = bytes.len().min(FX_WORD - self.ntail);
+            self.tail[self.ntail..self.ntail + fill].copy_from_slice(&bytes[..fill]);
+            self.ntail += fill;
+            bytes = &bytes[fill..];
+            if self.ntail < FX_WORD {
+                return;
+            }
+            self.ntail = 0;
+            self.add_to_hash(usize::from_ne_bytes(self.tail));
+        }
+        let mut chunks = bytes.chunks_exact(FX_WORD);
+        for chunk in &mut chunks {
+            self.add_to_hash(usize::from_ne_bytes(chunk.try_into().unwrap()));
+        }
+        let rest = chunks.remainder();
+        self.tail[..rest.len()].copy_from_slice(rest);
+        self.ntail = rest.len();
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add_int(i as usize);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.add_int(i as usize);
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.add_int(i as usize);
+    }
+
+    #[cfg(target_pointer_width = "64")]
+    fn write_u64(&mut self, i: u64) {
+        self.add_int(i as usize);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add_int(i);
+    }
+}
+
+/// A [`BuildHasher`] creating default [`FxHasher`]s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildFxHasher;
+
+impl BuildHasher for BuildFxHasher {
+    type Hasher = FxHasher;
+
+    fn build_hasher(&self) -> FxHasher {
+        FxHasher::default()
+    }
+}
+
+/// The reflected IEEE 802.3 polynomial, as used by zlib.
+const CRC32_POLY: u32 = 0xedb8_8320;
+
+/// The byte-at-a-time CRC-32 lookup table, generated at compile time.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32_POLY
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// A [CRC-32] hasher, computing the same checksum as zlib's `crc32`.
+///
+/// Meant for keys whose CRC is also computed elsewhere, such as by hardware,
+/// rather than for speed or collision resistance. Integers are written in
+/// little-endian order, and `usize`s as `u64`s, so the hash of a value is the
+/// same on every target.
+///
+/// [CRC-32]: https://en.wikipedia.org/wiki/Cyclic_redundancy_check
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32Hasher {
+    /// The running CRC, before the final inversion.
+    crc: u32,
+}
+
+impl Crc32Hasher {
+    /// Returns the CRC-32 of the bytes written so far.
+    pub fn crc32(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl Default for Crc32Hasher {
+    fn default() -> Self {
+        Self { crc: !0 }
+    }
+}
+
+impl Hasher for Crc32Hasher {
+    fn finish(&self) -> u64 {
+        self.crc32() as u64
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            let index = (self.crc as u8 ^ byte) as usize;
+            self.crc = (self.crc >> 8) ^ CRC32_TABLE[index];
+        }
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64)
+    }
+}
+
+/// A [`BuildHasher`] creating [`Crc32Hasher`]s.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BuildCrc32;
+
+impl BuildHasher for BuildCrc32 {
+    type Hasher = Crc32Hasher;
+
+    fn build_hasher(&self) -> Crc32Hasher {
+        Crc32Hasher::default()
+    }
+}
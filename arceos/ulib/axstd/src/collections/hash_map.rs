@@ -0,0 +1,1263 @@
+//! A hash map implemented with quadratic probing and Robin Hood insertion.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::fmt;
+use core::marker::PhantomData;
+
+use super::hash::{BuildDefaultHasher, BuildHasher, Hash, Hasher};
+use super::probe::{ideal_bucket, ProbeSeq};
+
+/// The number of buckets of a map created by [`HashMap::new`].
+///
+/// Kept small, as the map grows on demand. Use
+/// [`HashMap::new_with_capacity`] or [`HashMap::reserve`] when the number of
+/// entries is known up front.
+pub const DEFAULT_CAPACITY: usize = 16;
+
+/// The map grows once more than `MAX_LOAD_NUM / MAX_LOAD_DEN` of its buckets
+/// are in use.
+const MAX_LOAD_NUM: usize = 3;
+const MAX_LOAD_DEN: usize = 4;
+
+/// The fewest buckets a map is created with or shrunk to, so that a small
+/// map does not rehash on every few inserts and removes.
+pub const MIN_CAPACITY: usize = 8;
+
+/// Returns the number of buckets of a map created for `requested` of them:
+/// the next power of two, but no less than [`MIN_CAPACITY`].
+///
+/// # Panics
+///
+/// Panics if the result overflows `usize`.
+pub fn next_capacity(requested: usize) -> usize {
+    requested
+        .max(MIN_CAPACITY)
+        .checked_next_power_of_two()
+        .expect("capacity overflow")
+}
+
+/// A slot of the bucket array.
+#[derive(Clone)]
+enum Bucket<K, V> {
+    Empty,
+    Occupied(Slot<K, V>),
+    /// A removed entry. Lookups keep probing past it, and inserts may reuse
+    /// it.
+    Tombstone,
+}
+
+/// An entry stored in a bucket.
+#[derive(Clone)]
+struct Slot<K, V> {
+    key: K,
+    value: V,
+    /// Position of the bucket in `HashMap::entries`.
+    pos: usize,
+    /// Number of probe steps from the ideal bucket of the key to this one.
+    dist: usize,
+}
+
+/// A hash map with open addressing and quadratic probing.
+///
+/// Inserts use Robin Hood hashing: an entry further from its ideal bucket
+/// takes the place of one closer to its own, which moves on down its probe
+/// sequence. This keeps every probe sequence short, even at high load.
+///
+/// Keys are hashed with the hashers built by `S`, [`DefaultHasher`] by
+/// default. The bucket array doubles whenever the load factor exceeds 3/4.
+///
+/// The indices of the occupied buckets are also kept in a dense list, so
+/// iterating takes time proportional to the number of entries, however
+/// sparse the bucket array.
+///
+/// [`DefaultHasher`]: super::hash::DefaultHasher
+#[derive(Clone)]
+pub struct HashMap<K, V, S = BuildDefaultHasher> {
+    buckets: Vec<Bucket<K, V>>,
+    capacity: usize,
+    /// Indices of the occupied buckets, in no particular order.
+    entries: Vec<usize>,
+    /// Number of tombstone buckets.
+    tombstones: usize,
+    /// Inserts since the last rehash or clear whose ideal bucket was taken.
+    collisions: usize,
+    /// Positions in `entries`, in insertion order, once enabled by
+    /// [`HashMap::track_insertion_order`].
+    insertion_order: Option<Vec<usize>>,
+    build_hasher: S,
+}
+
+impl<K, V> HashMap<K, V>
+where
+    K: Eq + Hash,
+{
+    /// Creates an empty `HashMap` with the default capacity.
+    pub fn new() -> Self {
+        Self::new_with_capacity(None)
+    }
+
+    /// Creates an empty `HashMap` with at least `capacity` buckets, or the
+    /// default capacity if `None`.
+    ///
+    /// The capacity is rounded with [`next_capacity`], so `Some(1000)` gives
+    /// 1024 buckets. It is kept as a floor since the map only grows, unless
+    /// shrunk with `shrink_to_fit`.
+    pub fn new_with_capacity(capacity: Option<usize>) -> Self {
+        Self::with_hasher(
+            capacity.unwrap_or(DEFAULT_CAPACITY),
+            BuildDefaultHasher::default(),
+        )
+    }
+}
+
+impl<K, V> Default for HashMap<K, V>
+where
+    K: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Creates an empty `HashMap` with at least `capacity` buckets, which
+    /// hashes keys with hashers built by `hasher`.
+    ///
+    /// The capacity is rounded with [`next_capacity`].
+    pub fn with_hasher(capacity: usize, hasher: S) -> Self {
+        let capacity = next_capacity(capacity);
+        Self {
+            buckets: empty_buckets(capacity),
+            capacity,
+            entries: Vec::new(),
+            tombstones: 0,
+            collisions: 0,
+            insertion_order: None,
+            build_hasher: hasher,
+        }
+    }
+
+    /// Creates an empty `HashMap` sized like [`HashMap::new_with_capacity`],
+    /// which hashes keys with hashers built by `hasher`.
+    ///
+    /// This is [`HashMap::with_hasher`] under the name `std` uses.
+    pub fn with_capacity_and_hasher(capacity: usize, hasher: S) -> Self {
+        Self::with_hasher(capacity, hasher)
+    }
+
+    /// Returns the ideal bucket of `k`, which is the start of its probe
+    /// sequence.
+    ///
+    /// The capacity is a power of two, so the hash is masked instead of
+    /// taken modulo. As only the low bits are kept, the high bits are folded
+    /// into them first (`hash ^ hash >> 32`): FNV mixes its last bytes poorly
+    /// into the low bits.
+    ///
+    /// The result is folded once more, by 16 bits. Strings all end with the
+    /// terminator of [`Hasher::write_str`], and a single fold still put 91 of
+    /// 3000 `key_{i}` keys in one 64-bucket range out of 4096 buckets, where
+    /// about 47 are expected.
+    fn hash<Q: Hash + ?Sized>(&self, k: &Q) -> usize {
+        let mut hasher = self.build_hasher.build_hasher();
+        k.hash(&mut hasher);
+        ideal_bucket(hasher.finish(), self.capacity)
+    }
+
+    /// Returns the buckets to visit for a key whose ideal bucket is `ideal`,
+    /// in order.
+    fn probe_seq(&self, ideal: usize) -> ProbeSeq {
+        ProbeSeq::new(ideal, self.capacity)
+    }
+
+    /// Returns the index of the bucket holding `k`.
+    fn find<Q>(&self, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        if self.capacity == 0 {
+            return None;
+        }
+        self.find_from(self.hash(k), k)
+    }
+
+    /// Returns the index of the bucket holding `k`, whose ideal bucket is
+    /// `ideal`.
+    fn find_from<Q>(&self, ideal: usize, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        for index in self.probe_seq(ideal) {
+            match &self.buckets[index] {
+                Bucket::Empty => return None,
+                Bucket::Occupied(slot) if slot.key.borrow() == k => return Some(index),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Returns `Ok` with the bucket holding `k`, or `Err` with the ideal
+    /// bucket of `k`, from which [`HashMap::place`] inserts it.
+    ///
+    /// The map must have an empty bucket, which `self.reserve(1)` ensures.
+    fn probe(&self, k: &K) -> Result<usize, usize> {
+        let ideal = self.hash(k);
+        self.find_from(ideal, k).ok_or(ideal)
+    }
+
+    /// Inserts a key-value pair into the map, returning the previous value
+    /// of the key if it was present.
+    pub fn insert(&mut self, k: K, v: V) -> Option<V> {
+        self.reserve(1);
+        match self.probe(&k) {
+            Ok(index) => Some(core::mem::replace(self.value_mut(index), v)),
+            Err(ideal) => {
+                self.place(ideal, k, v);
+                None
+            }
+        }
+    }
+
+    /// Inserts a key-value pair if the key is absent, returning a mutable
+    /// reference to the new value.
+    ///
+    /// If the key is already present, nothing is updated, and the error
+    /// carries both the existing entry and the rejected value.
+    pub fn try_insert(&mut self, k: K, v: V) -> Result<&mut V, OccupiedError<'_, K, V, S>> {
+        match self.entry(k) {
+            Entry::Occupied(entry) => Err(OccupiedError { entry, value: v }),
+            Entry::Vacant(entry) => Ok(entry.insert(v)),
+        }
+    }
+
+    /// Calls `update` on the value of the key if it is present, or inserts
+    /// `default` otherwise, with a single probe.
+    ///
+    /// `update` is not called on the inserted value.
+    pub fn update_or_insert<F: FnOnce(&mut V)>(&mut self, k: K, default: V, update: F) {
+        self.reserve(1);
+        match self.probe(&k) {
+            Ok(index) => update(self.value_mut(index)),
+            Err(ideal) => {
+                self.place(ideal, k, default);
+            }
+        }
+    }
+
+    /// Returns the value of the key, inserting the result of `make` first
+    /// if it is absent, with a single probe.
+    ///
+    /// `make` is only called if the key is absent.
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: K, make: F) -> &mut V {
+        self.reserve(1);
+        match self.probe(&k) {
+            Ok(index) => self.value_mut(index),
+            Err(ideal) => self.place(ideal, k, make()),
+        }
+    }
+
+    /// Moves every entry of `other` into `self`. For keys present in both,
+    /// the value becomes `resolve(key, existing, incoming)`.
+    ///
+    /// The key already stored in `self` is kept.
+    pub fn merge_with<T, F>(&mut self, other: HashMap<K, V, T>, mut resolve: F)
+    where
+        F: FnMut(&K, V, V) -> V,
+    {
+        for (k, incoming) in other {
+            self.reserve(1);
+            match self.probe(&k) {
+                Ok(index) => {
+                    let rank = self.insertion_rank(index);
+                    let collisions = self.collisions;
+                    let (key, existing) = self.vacate(index);
+                    let value = resolve(&key, existing, incoming);
+                    let ideal = self.hash(&key);
+                    self.place(ideal, key, value);
+                    // The key was counted when it was first inserted.
+                    self.collisions = collisions;
+                    // Merging is an update: keep the key in its place.
+                    if let (Some(order), Some(rank)) = (&mut self.insertion_order, rank) {
+                        let pos = order.pop().unwrap();
+                        order.insert(rank, pos);
+                    }
+                }
+                Err(ideal) => {
+                    self.place(ideal, k, incoming);
+                }
+            }
+        }
+    }
+
+    /// Gets the entry of the key for in-place manipulation.
+    pub fn entry(&mut self, k: K) -> Entry<'_, K, V, S> {
+        self.reserve(1);
+        match self.probe(&k) {
+            Ok(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            Err(ideal) => Entry::Vacant(VacantEntry {
+                map: self,
+                key: k,
+                ideal,
+            }),
+        }
+    }
+
+    /// Gets the entry of a borrowed key, like [`HashMap::entry`].
+    ///
+    /// The owned key is only built, with `K::from(k)`, when a vacant entry
+    /// is inserted into, so a lookup that finds the key allocates nothing.
+    pub fn entry_ref<'a, 'b, Q>(&'a mut self, k: &'b Q) -> EntryRef<'a, 'b, K, V, Q, S>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.reserve(1);
+        let ideal = self.hash(k);
+        match self.find_from(ideal, k) {
+            Some(index) => EntryRef::Occupied(OccupiedEntry { map: self, index }),
+            None => EntryRef::Vacant(VacantEntryRef {
+                map: self,
+                key: k,
+                ideal,
+            }),
+        }
+    }
+
+    /// Makes room for at least `additional` more entries, so that inserting
+    /// them doesn't rehash. Does nothing if there is room already.
+    ///
+    /// Tombstones count toward the load, but only live entries make the map
+    /// grow: a table clogged with tombstones is rebuilt at the same size.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of buckets needed overflows `usize`.
+    pub fn reserve(&mut self, additional: usize) {
+        let needed = self
+            .len()
+            .checked_add(additional)
+            .expect("capacity overflow");
+        if fits(needed.saturating_add(self.tombstones), self.capacity) {
+            return;
+        }
+        let min_cap = needed
+            .checked_mul(MAX_LOAD_DEN)
+            .expect("capacity overflow")
+            .div_ceil(MAX_LOAD_NUM);
+        let new_cap = min_cap
+            .max(self.capacity)
+            .checked_next_power_of_two()
+            .expect("capacity overflow");
+        self.rehash(new_cap);
+    }
+
+    /// Shrinks the bucket array to the smallest power of two that holds the
+    /// current entries within the load factor, but no less than
+    /// [`MIN_CAPACITY`] buckets.
+    ///
+    /// Does nothing if the map is already that small.
+    pub fn shrink_to_fit(&mut self) {
+        let mut new_cap = MIN_CAPACITY;
+        while !fits(self.len(), new_cap) {
+            new_cap *= 2;
+        }
+        if new_cap < self.capacity {
+            self.rehash(new_cap);
+        }
+    }
+
+    /// Moves every entry into a fresh array of `new_cap` buckets, dropping
+    /// all tombstones.
+    ///
+    /// Entries keep their position in the dense list.
+    fn rehash(&mut self, new_cap: usize) {
+        let old = core::mem::replace(&mut self.buckets, empty_buckets(new_cap));
+        self.capacity = new_cap;
+        self.tombstones = 0;
+        self.collisions = 0;
+        for bucket in old {
+            if let Bucket::Occupied(mut slot) = bucket {
+                slot.dist = 0;
+                let ideal = self.hash(&slot.key);
+                self.place_slot(ideal, slot);
+            }
+        }
+    }
+
+    /// Returns a reference to the value of the key.
+    ///
+    /// The key may be any borrowed form of the key type, such as a `&str`
+    /// for `String` keys, as long as `Hash` and `Eq` agree on both forms.
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        match &self.buckets[self.find(k)?] {
+            Bucket::Occupied(slot) => Some(&slot.value),
+            _ => None,
+        }
+    }
+
+    /// Returns the stored key and a reference to the value of the key.
+    ///
+    /// The stored key may differ from `k`, in the parts ignored by `Eq`.
+    pub fn get_key_value<Q>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        Some(self.entry_at(self.find(k)?))
+    }
+
+    /// Returns a mutable reference to the value of the key.
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.find(k)?;
+        match &mut self.buckets[index] {
+            Bucket::Occupied(slot) => Some(&mut slot.value),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    pub fn contains_key<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.find(k).is_some()
+    }
+
+    /// Removes a key from the map, returning its value if it was present.
+    ///
+    /// The bucket is left as a tombstone, so keys further along the same
+    /// probe chain are still found.
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let index = self.find(k)?;
+        Some(self.vacate(index).1)
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    /// Returns the number of buckets.
+    ///
+    /// The map grows before more than 3/4 of them are in use.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Removes all entries, keeping the allocated buckets for reuse.
+    pub fn clear(&mut self) {
+        for bucket in &mut self.buckets {
+            *bucket = Bucket::Empty;
+        }
+        self.entries.clear();
+        self.tombstones = 0;
+        self.collisions = 0;
+        if let Some(order) = &mut self.insertion_order {
+            order.clear();
+        }
+    }
+
+    /// Removes all entries, returning them as an iterator. The capacity is
+    /// kept.
+    ///
+    /// The map is emptied right away: it gets a new bucket array, and the
+    /// iterator takes the old one. Entries not consumed are dropped with the
+    /// iterator, or only leaked if it is leaked.
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        let buckets = core::mem::replace(&mut self.buckets, empty_buckets(self.capacity));
+        self.tombstones = 0;
+        self.collisions = 0;
+        if let Some(order) = &mut self.insertion_order {
+            order.clear();
+        }
+        Drain {
+            buckets,
+            entries: self.entries.drain(..),
+        }
+    }
+
+    /// Keeps only the entries for which `f` returns `true`.
+    ///
+    /// Removed entries leave tombstones, like [`HashMap::remove`].
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        // Walk the dense list backwards: removing an entry moves the last
+        // one into its place, which has been visited already.
+        for pos in (0..self.entries.len()).rev() {
+            let index = self.entries[pos];
+            if let Bucket::Occupied(slot) = &mut self.buckets[index] {
+                if !f(&slot.key, &mut slot.value) {
+                    self.vacate(index);
+                }
+            }
+        }
+    }
+
+    /// Keeps only the entries whose keys `f` returns `true` for, like
+    /// [`HashMap::retain`] without borrowing the values.
+    pub fn retain_keys<F: FnMut(&K) -> bool>(&mut self, mut f: F) {
+        for pos in (0..self.entries.len()).rev() {
+            let index = self.entries[pos];
+            if let Bucket::Occupied(slot) = &self.buckets[index] {
+                if !f(&slot.key) {
+                    self.vacate(index);
+                }
+            }
+        }
+    }
+
+    /// Returns how many inserts found their ideal bucket taken and had to
+    /// probe further, since the map was last rehashed or cleared.
+    ///
+    /// Compared to [`HashMap::len`], this tells how well the hasher spreads
+    /// the keys being inserted.
+    pub fn collision_count(&self) -> usize {
+        self.collisions
+    }
+
+    /// Returns the longest probe length of any entry, or 0 if the map is
+    /// empty.
+    ///
+    /// The probe length of an entry is the number of probe steps from the
+    /// ideal bucket of its key to the bucket holding it, so 0 if it sits in
+    /// its ideal bucket. Long probes point to a hasher clustering the keys.
+    pub fn max_probe_length(&self) -> usize {
+        self.slots().map(|slot| slot.dist).max().unwrap_or(0)
+    }
+
+    /// Returns the mean probe length over all entries, or 0 if the map is
+    /// empty. See [`HashMap::max_probe_length`].
+    pub fn average_probe_length(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let total: usize = self.slots().map(|slot| slot.dist).sum();
+        total as f64 / self.len() as f64
+    }
+
+    /// Returns an iterator over all key-value pairs, in arbitrary order.
+    ///
+    /// Iterating puts no requirement on the keys or values:
+    ///
+    /// ```
+    /// use axstd::collections::HashMap;
+    ///
+    /// struct Handle(u32);
+    ///
+    /// let mut map = HashMap::new_with_capacity(Some(4));
+    /// map.insert(1u32, Handle(10));
+    /// map.insert(2, Handle(20));
+    /// let total: u32 = map.iter().map(|(_, handle)| handle.0).sum();
+    /// assert_eq!(total, 30);
+    /// ```
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter {
+            buckets: &self.buckets,
+            entries: self.entries.iter(),
+        }
+    }
+
+    /// Starts recording the order in which entries are inserted, for
+    /// [`HashMap::iter_ordered`]. Entries already present come first, in
+    /// arbitrary order.
+    ///
+    /// The order costs a `usize` per entry, and makes removals take time
+    /// proportional to the number of entries.
+    pub fn track_insertion_order(&mut self) {
+        if self.insertion_order.is_none() {
+            self.insertion_order = Some((0..self.entries.len()).collect());
+        }
+    }
+
+    /// Returns an iterator over all entries, in insertion order.
+    ///
+    /// Updating the value of a key keeps its place, and a removed key that
+    /// is inserted again goes last.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`HashMap::track_insertion_order`] was not called.
+    pub fn iter_ordered(&self) -> impl Iterator<Item = (&K, &V)> {
+        let order = self
+            .insertion_order
+            .as_ref()
+            .expect("insertion order is not tracked");
+        order.iter().map(|&pos| self.entry_at(self.entries[pos]))
+    }
+
+    /// Returns an iterator over all keys, in arbitrary order.
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Returns an iterator over all values, in arbitrary order.
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    /// Returns an iterator over mutable references to all values, in
+    /// arbitrary order.
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut {
+            buckets: self.buckets.as_mut_ptr(),
+            entries: self.entries.iter(),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<K, V, S> HashMap<K, V, S> {
+    /// Inserts a new entry for a key whose ideal bucket is `ideal`,
+    /// returning a reference to its value.
+    fn place(&mut self, ideal: usize, k: K, v: V) -> &mut V {
+        if let Bucket::Occupied(_) = self.buckets[ideal] {
+            self.collisions += 1;
+        }
+        let slot = Slot {
+            key: k,
+            value: v,
+            pos: self.entries.len(),
+            dist: 0,
+        };
+        // Filled in once the entry has a bucket.
+        self.entries.push(ideal);
+        if let Some(order) = &mut self.insertion_order {
+            order.push(slot.pos);
+        }
+        let index = self.place_slot(ideal, slot);
+        self.value_mut(index)
+    }
+
+    /// Stores `slot` along the probe sequence starting at `ideal`, in the
+    /// first empty or tombstone bucket, or in place of an entry closer to
+    /// its own ideal bucket. Returns the bucket `slot` ends up in.
+    ///
+    /// A displaced entry carries on down its own sequence, possibly
+    /// displacing others in turn, until one lands in a free bucket.
+    fn place_slot(&mut self, ideal: usize, mut slot: Slot<K, V>) -> usize {
+        let mut index = ideal;
+        let mut placed = None;
+        loop {
+            match &mut self.buckets[index] {
+                Bucket::Occupied(other) if other.dist >= slot.dist => {}
+                Bucket::Occupied(other) => {
+                    core::mem::swap(other, &mut slot);
+                    self.entries[other.pos] = index;
+                    placed.get_or_insert(index);
+                }
+                bucket => {
+                    if let Bucket::Tombstone = bucket {
+                        self.tombstones -= 1;
+                    }
+                    self.entries[slot.pos] = index;
+                    *bucket = Bucket::Occupied(slot);
+                    return placed.unwrap_or(index);
+                }
+            }
+            // The sequence of a key steps by 1, 2, 3... from its ideal bucket.
+            slot.dist += 1;
+            index = (index + slot.dist) & (self.capacity - 1);
+        }
+    }
+
+    /// Takes the entry out of the occupied bucket at `index`, leaving a
+    /// tombstone.
+    ///
+    /// The last entry of the dense list takes the place of the removed one.
+    fn vacate(&mut self, index: usize) -> (K, V) {
+        match core::mem::replace(&mut self.buckets[index], Bucket::Tombstone) {
+            Bucket::Occupied(slot) => {
+                self.entries.swap_remove(slot.pos);
+                if let Some(&moved) = self.entries.get(slot.pos) {
+                    match &mut self.buckets[moved] {
+                        Bucket::Occupied(other) => other.pos = slot.pos,
+                        _ => unreachable!("bucket {moved} is not occupied"),
+                    }
+                }
+                if let Some(order) = &mut self.insertion_order {
+                    order.retain(|&pos| pos != slot.pos);
+                    // The last entry took the place of the removed one.
+                    let last = self.entries.len();
+                    if let Some(pos) = order.iter_mut().find(|pos| **pos == last) {
+                        *pos = slot.pos;
+                    }
+                }
+                self.tombstones += 1;
+                (slot.key, slot.value)
+            }
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    /// Returns where the entry in bucket `index` stands in the insertion
+    /// order, if it is tracked.
+    fn insertion_rank(&self, index: usize) -> Option<usize> {
+        let order = self.insertion_order.as_ref()?;
+        match &self.buckets[index] {
+            Bucket::Occupied(slot) => order.iter().position(|&pos| pos == slot.pos),
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    /// Returns the slots of all entries, through the dense list.
+    fn slots(&self) -> impl Iterator<Item = &Slot<K, V>> {
+        self.entries
+            .iter()
+            .map(|&index| match &self.buckets[index] {
+                Bucket::Occupied(slot) => slot,
+                _ => unreachable!("bucket {index} is not occupied"),
+            })
+    }
+
+    fn entry_at(&self, index: usize) -> (&K, &V) {
+        match &self.buckets[index] {
+            Bucket::Occupied(slot) => (&slot.key, &slot.value),
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    fn value_mut(&mut self, index: usize) -> &mut V {
+        match &mut self.buckets[index] {
+            Bucket::Occupied(slot) => &mut slot.value,
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    /// Checks that the dense list and the buckets agree.
+    #[cfg(test)]
+    pub(super) fn assert_entries_consistent(&self) {
+        for (pos, &index) in self.entries.iter().enumerate() {
+            assert!(
+                matches!(&self.buckets[index], Bucket::Occupied(slot) if slot.pos == pos),
+                "entry {pos} points to bucket {index}, which does not point back",
+            );
+        }
+        let occupied = self
+            .buckets
+            .iter()
+            .filter(|bucket| matches!(bucket, Bucket::Occupied(..)))
+            .count();
+        assert_eq!(occupied, self.entries.len());
+        let tombstones = self
+            .buckets
+            .iter()
+            .filter(|bucket| matches!(bucket, Bucket::Tombstone))
+            .count();
+        assert_eq!(tombstones, self.tombstones);
+        if let Some(order) = &self.insertion_order {
+            let mut sorted = order.clone();
+            sorted.sort_unstable();
+            assert!(sorted.into_iter().eq(0..self.entries.len()));
+        }
+    }
+}
+
+/// Returns `true` if `used` buckets stay within the load factor of a map
+/// with `capacity` buckets.
+fn fits(used: usize, capacity: usize) -> bool {
+    used.checked_mul(MAX_LOAD_DEN)
+        .is_some_and(|used| used <= capacity.saturating_mul(MAX_LOAD_NUM))
+}
+
+/// Returns `n` empty buckets, without requiring `K` or `V` to be `Clone`.
+fn empty_buckets<K, V>(n: usize) -> Vec<Bucket<K, V>> {
+    core::iter::repeat_with(|| Bucket::Empty).take(n).collect()
+}
+
+/// A view into a single entry of a [`HashMap`], from [`HashMap::entry`].
+pub enum Entry<'a, K, V, S = BuildDefaultHasher> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    /// A vacant entry.
+    Vacant(VacantEntry<'a, K, V, S>),
+}
+
+impl<'a, K, V, S> Entry<'a, K, V, S> {
+    /// Ensures a value is in the entry by inserting `default` if empty, and
+    /// returns a mutable reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default`
+    /// if empty, and returns a mutable reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if
+    /// empty, and returns a mutable reference to the value.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(V::default()),
+        }
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    /// Returns the key of this entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+/// An occupied entry of a [`HashMap`]. Part of the [`Entry`] enum.
+pub struct OccupiedEntry<'a, K, V, S = BuildDefaultHasher> {
+    map: &'a mut HashMap<K, V, S>,
+    index: usize,
+}
+
+impl<'a, K, V, S> OccupiedEntry<'a, K, V, S> {
+    /// Returns the key in the entry.
+    pub fn key(&self) -> &K {
+        self.map.entry_at(self.index).0
+    }
+
+    /// Returns a reference to the value in the entry.
+    pub fn get(&self) -> &V {
+        self.map.entry_at(self.index).1
+    }
+
+    /// Returns a mutable reference to the value in the entry.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.map.value_mut(self.index)
+    }
+
+    /// Converts the entry into a mutable reference to its value, bound to
+    /// the lifetime of the map.
+    pub fn into_mut(self) -> &'a mut V {
+        self.map.value_mut(self.index)
+    }
+
+    /// Sets the value of the entry, returning the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        core::mem::replace(self.get_mut(), value)
+    }
+
+    /// Takes the entry out of the map, returning its value.
+    pub fn remove(self) -> V {
+        self.map.vacate(self.index).1
+    }
+}
+
+/// The error returned by [`HashMap::try_insert`] when the key is already
+/// present.
+pub struct OccupiedError<'a, K, V, S = BuildDefaultHasher> {
+    /// The entry already holding the key.
+    pub entry: OccupiedEntry<'a, K, V, S>,
+    /// The value that was not inserted.
+    pub value: V,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for OccupiedError<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OccupiedError")
+            .field("key", self.entry.key())
+            .field("old_value", self.entry.get())
+            .field("new_value", &self.value)
+            .finish()
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Display for OccupiedError<'_, K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "failed to insert {:?}, key {:?} already exists with value {:?}",
+            self.value,
+            self.entry.key(),
+            self.entry.get(),
+        )
+    }
+}
+
+/// A vacant entry of a [`HashMap`]. Part of the [`Entry`] enum.
+pub struct VacantEntry<'a, K, V, S = BuildDefaultHasher> {
+    map: &'a mut HashMap<K, V, S>,
+    key: K,
+    /// The ideal bucket of the key.
+    ideal: usize,
+}
+
+impl<'a, K, V, S> VacantEntry<'a, K, V, S> {
+    /// Returns the key that would be used when inserting.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts `value` with the entry's key, returning a mutable reference
+    /// to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.place(self.ideal, self.key, value)
+    }
+}
+
+/// A view into a single entry of a [`HashMap`], from [`HashMap::entry_ref`].
+pub enum EntryRef<'a, 'b, K, V, Q: ?Sized, S = BuildDefaultHasher> {
+    /// An occupied entry.
+    Occupied(OccupiedEntry<'a, K, V, S>),
+    /// A vacant entry.
+    Vacant(VacantEntryRef<'a, 'b, K, V, Q, S>),
+}
+
+impl<'a, 'b, K, V, Q, S> EntryRef<'a, 'b, K, V, Q, S>
+where
+    K: Borrow<Q> + From<&'b Q>,
+    Q: ?Sized,
+{
+    /// Ensures a value is in the entry by inserting `default` if empty, and
+    /// returns a mutable reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the result of `default`
+    /// if empty, and returns a mutable reference to the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    /// Ensures a value is in the entry by inserting the default value if
+    /// empty, and returns a mutable reference to the value.
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        match self {
+            EntryRef::Occupied(entry) => entry.into_mut(),
+            EntryRef::Vacant(entry) => entry.insert(V::default()),
+        }
+    }
+
+    /// Calls `f` on the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let EntryRef::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+
+    /// Returns the key of this entry.
+    pub fn key(&self) -> &Q {
+        match self {
+            EntryRef::Occupied(entry) => entry.key().borrow(),
+            EntryRef::Vacant(entry) => entry.key(),
+        }
+    }
+}
+
+/// A vacant entry of a [`HashMap`]. Part of the [`EntryRef`] enum.
+pub struct VacantEntryRef<'a, 'b, K, V, Q: ?Sized, S = BuildDefaultHasher> {
+    map: &'a mut HashMap<K, V, S>,
+    key: &'b Q,
+    /// The ideal bucket of the key.
+    ideal: usize,
+}
+
+impl<'a, 'b, K, V, Q: ?Sized, S> VacantEntryRef<'a, 'b, K, V, Q, S> {
+    /// Returns the borrowed key that would be used when inserting.
+    pub fn key(&self) -> &'b Q {
+        self.key
+    }
+
+    /// Inserts `value` with an owned key made from the borrowed one,
+    /// returning a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V
+    where
+        K: From<&'b Q>,
+    {
+        self.map.place(self.ideal, K::from(self.key), value)
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S> fmt::Debug for HashMap<K, V, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, Q, V, S> core::ops::Index<&Q> for HashMap<K, V, S>
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Eq + Hash + ?Sized,
+    S: BuildHasher,
+{
+    type Output = V;
+
+    /// Returns a reference to the value of the key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not present in the map.
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("no entry found for key")
+    }
+}
+
+/// Two maps are equal if they hold the same entries, whatever the layout of
+/// their buckets.
+impl<K, V, S> PartialEq for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: PartialEq,
+    S: BuildHasher,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(k, v)| other.get(k).map_or(false, |other_v| v == other_v))
+    }
+}
+
+impl<K, V, S> Eq for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    V: Eq,
+    S: BuildHasher,
+{
+}
+
+impl<K, V, S> FromIterator<(K, V)> for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::with_hasher(DEFAULT_CAPACITY, S::default());
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K, V, S> Extend<(K, V)> for HashMap<K, V, S>
+where
+    K: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+/// An iterator over the entries of a [`HashMap`].
+pub struct Iter<'a, K, V> {
+    buckets: &'a [Bucket<K, V>],
+    /// Indices of the buckets not yielded yet.
+    entries: core::slice::Iter<'a, usize>,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = *self.entries.next()?;
+        match &self.buckets[index] {
+            Bucket::Occupied(slot) => Some((&slot.key, &slot.value)),
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+/// An iterator over the keys of a [`HashMap`].
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Keys<'_, K, V> {}
+
+/// An iterator over the values of a [`HashMap`].
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Values<'_, K, V> {}
+
+/// A mutable iterator over the values of a [`HashMap`].
+pub struct ValuesMut<'a, K, V> {
+    /// The bucket array, borrowed mutably for `'a`.
+    buckets: *mut Bucket<K, V>,
+    /// Indices of the buckets not yielded yet.
+    entries: core::slice::Iter<'a, usize>,
+    marker: PhantomData<&'a mut [Bucket<K, V>]>,
+}
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = *self.entries.next()?;
+        // SAFETY: the indices in `entries` are in bounds and distinct, so
+        // each bucket is borrowed at most once.
+        match unsafe { &mut *self.buckets.add(index) } {
+            Bucket::Occupied(slot) => Some(&mut slot.value),
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for ValuesMut<'_, K, V> {}
+
+// The iterator hands out `&mut V` and reads the keys, like a
+// `&mut [Bucket<K, V>]`.
+unsafe impl<K: Send, V: Send> Send for ValuesMut<'_, K, V> {}
+unsafe impl<K: Sync, V: Sync> Sync for ValuesMut<'_, K, V> {}
+
+impl<K, V, S> IntoIterator for HashMap<K, V, S> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            buckets: self.buckets,
+            entries: self.entries.into_iter(),
+        }
+    }
+}
+
+/// An owning iterator over the entries of a [`HashMap`].
+pub struct IntoIter<K, V> {
+    buckets: Vec<Bucket<K, V>>,
+    /// Indices of the buckets not yielded yet.
+    entries: vec::IntoIter<usize>,
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.entries.next()?;
+        match core::mem::replace(&mut self.buckets[index], Bucket::Empty) {
+            Bucket::Occupied(slot) => Some((slot.key, slot.value)),
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for IntoIter<K, V> {}
+
+/// A draining iterator over the entries of a [`HashMap`], from
+/// [`HashMap::drain`].
+///
+/// It owns the old bucket array of the map, which drops the entries left
+/// when it is dropped.
+pub struct Drain<'a, K, V> {
+    buckets: Vec<Bucket<K, V>>,
+    /// Indices of the buckets not drained yet.
+    entries: vec::Drain<'a, usize>,
+}
+
+impl<K, V> Iterator for Drain<'_, K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.entries.next()?;
+        match core::mem::replace(&mut self.buckets[index], Bucket::Empty) {
+            Bucket::Occupied(slot) => Some((slot.key, slot.value)),
+            _ => unreachable!("bucket {index} is not occupied"),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for Drain<'_, K, V> {}
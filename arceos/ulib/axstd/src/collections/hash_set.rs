@@ -0,0 +1,226 @@
+//! A hash set implemented as a [`HashMap`] with `()` values.
+
+use core::borrow::Borrow;
+use core::fmt;
+
+use super::hash::{BuildDefaultHasher, BuildHasher, Hash};
+use super::hash_map::{self, HashMap};
+
+/// A hash set, backed by a [`HashMap`] with `()` values.
+///
+/// It shares the map's probing and resizing, and hashes with `S` likewise.
+#[derive(Clone)]
+pub struct HashSet<T, S = BuildDefaultHasher> {
+    map: HashMap<T, (), S>,
+}
+
+impl<T> HashSet<T>
+where
+    T: Eq + Hash,
+{
+    /// Creates an empty `HashSet` with the default capacity.
+    pub fn new() -> Self {
+        Self::new_with_capacity(None)
+    }
+
+    /// Creates an empty `HashSet` with at least `capacity` buckets, or the
+    /// default capacity if `None`.
+    pub fn new_with_capacity(capacity: Option<usize>) -> Self {
+        Self {
+            map: HashMap::new_with_capacity(capacity),
+        }
+    }
+}
+
+impl<T, S> HashSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    /// Creates an empty `HashSet` with at least `capacity` buckets, which
+    /// hashes values with hashers built by `hasher`.
+    pub fn with_hasher(capacity: usize, hasher: S) -> Self {
+        Self {
+            map: HashMap::with_hasher(capacity, hasher),
+        }
+    }
+
+    /// Adds a value to the set, returning `true` if it was not present.
+    pub fn insert(&mut self, value: T) -> bool {
+        self.map.insert(value, ()).is_none()
+    }
+
+    /// Returns `true` if the set contains the value.
+    ///
+    /// Like [`HashMap::get`], this takes any borrowed form of the value.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.contains_key(value)
+    }
+
+    /// Removes a value from the set, returning `true` if it was present.
+    pub fn remove<Q>(&mut self, value: &Q) -> bool
+    where
+        T: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.map.remove(value).is_some()
+    }
+
+    /// Returns an iterator over the values in `self` or `other`, without
+    /// duplicates.
+    pub fn union<'a>(&'a self, other: &'a Self) -> Union<'a, T, S> {
+        Union {
+            iter: self.iter().chain(other.difference(self)),
+        }
+    }
+
+    /// Returns an iterator over the values in both `self` and `other`.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> Intersection<'a, T, S> {
+        Intersection {
+            iter: self.iter(),
+            other,
+        }
+    }
+
+    /// Returns an iterator over the values in `self` but not in `other`.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> Difference<'a, T, S> {
+        Difference {
+            iter: self.iter(),
+            other,
+        }
+    }
+}
+
+impl<T, S> HashSet<T, S> {
+    /// Returns the number of values in the set.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the set contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns an iterator over all values, in arbitrary order.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            keys: self.map.keys(),
+        }
+    }
+}
+
+impl<T> Default for HashSet<T>
+where
+    T: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, S> fmt::Debug for HashSet<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<T, S> FromIterator<T> for HashSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher + Default,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self {
+            map: iter.into_iter().map(|value| (value, ())).collect(),
+        }
+    }
+}
+
+impl<T, S> Extend<T> for HashSet<T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.map.extend(iter.into_iter().map(|value| (value, ())));
+    }
+}
+
+/// An iterator over the values of a [`HashSet`].
+pub struct Iter<'a, T> {
+    keys: hash_map::Keys<'a, T, ()>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.keys.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.keys.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+/// An iterator over the union of two [`HashSet`]s.
+pub struct Union<'a, T, S> {
+    iter: core::iter::Chain<Iter<'a, T>, Difference<'a, T, S>>,
+}
+
+impl<'a, T, S> Iterator for Union<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.iter.next()
+    }
+}
+
+/// An iterator over the intersection of two [`HashSet`]s.
+pub struct Intersection<'a, T, S> {
+    iter: Iter<'a, T>,
+    other: &'a HashSet<T, S>,
+}
+
+impl<'a, T, S> Iterator for Intersection<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let other = self.other;
+        self.iter.by_ref().find(|value| other.contains(value))
+    }
+}
+
+/// An iterator over the difference of two [`HashSet`]s.
+pub struct Difference<'a, T, S> {
+    iter: Iter<'a, T>,
+    other: &'a HashSet<T, S>,
+}
+
+impl<'a, T, S> Iterator for Difference<'a, T, S>
+where
+    T: Eq + Hash,
+    S: BuildHasher,
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        let other = self.other;
+        self.iter.by_ref().find(|value| !other.contains(value))
+    }
+}
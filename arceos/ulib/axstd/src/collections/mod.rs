@@ -0,0 +1,48 @@
+//! Collection types.
+//!
+//! With the `alloc` feature, everything in [`alloc::collections`] is
+//! re-exported, plus a [`HashMap`], a [`HashSet`] and a [`BloomFilter`] built
+//! on the hashing primitives in [`hash`]. [`MultiMap`] nests a [`HashMap`] in
+//! another, and [`RwHashMap`] shares one between threads.
+//!
+//! [`ArrayHashMap`] holds a fixed number of entries without allocating, so
+//! it is available without the `alloc` feature, like [`hash`].
+//!
+//! As in `std`, each type lives in a public module of its own, along with
+//! its iterators and errors, and the main types are re-exported here.
+
+pub mod array_hash_map;
+#[cfg(feature = "alloc")]
+pub mod bloom_filter;
+pub mod hash;
+#[cfg(feature = "alloc")]
+pub mod hash_map;
+#[cfg(feature = "alloc")]
+pub mod hash_set;
+#[cfg(feature = "alloc")]
+pub mod multi_map;
+mod probe;
+#[cfg(feature = "alloc")]
+pub mod rw_hash_map;
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests;
+
+#[cfg(feature = "alloc")]
+#[doc(no_inline)]
+pub use alloc::collections::*;
+
+pub use self::array_hash_map::{ArrayHashMap, FullError};
+#[cfg(feature = "alloc")]
+pub use self::bloom_filter::BloomFilter;
+#[cfg(feature = "alloc")]
+pub use self::hash_map::{
+    next_capacity, Drain, Entry, EntryRef, HashMap, IntoIter, Iter, Keys, OccupiedEntry,
+    OccupiedError, VacantEntry, VacantEntryRef, Values, ValuesMut, DEFAULT_CAPACITY, MIN_CAPACITY,
+};
+#[cfg(feature = "alloc")]
+pub use self::hash_set::HashSet;
+#[cfg(feature = "alloc")]
+pub use self::multi_map::MultiMap;
+#[cfg(feature = "alloc")]
+pub use self::rw_hash_map::{RawRwLock, ReadGuard, RwHashMap, SpinRwLock};
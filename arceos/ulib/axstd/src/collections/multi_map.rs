@@ -0,0 +1,176 @@
+//! A two-level [`HashMap`], grouping values by an outer key.
+
+use core::borrow::Borrow;
+use core::fmt;
+
+use super::hash::{BuildDefaultHasher, BuildHasher, Hash};
+use super::{HashMap, DEFAULT_CAPACITY, MIN_CAPACITY};
+
+/// A map from `(K1, K2)` pairs to values, stored as a map of groups: a
+/// `HashMap<K1, HashMap<K2, V>>`, e.g. per-process tables of file
+/// descriptors.
+///
+/// Inner maps are created on the first insert into their group, with the
+/// fewest buckets, and dropped once emptied. Both levels grow on their own
+/// like any [`HashMap`], and hash with hashers built by clones of `S`.
+#[derive(Clone)]
+pub struct MultiMap<K1, K2, V, S = BuildDefaultHasher> {
+    groups: HashMap<K1, HashMap<K2, V, S>, S>,
+    /// Number of values in all groups.
+    len: usize,
+    build_hasher: S,
+}
+
+impl<K1, K2, V> MultiMap<K1, K2, V>
+where
+    K1: Eq + Hash,
+    K2: Eq + Hash,
+{
+    /// Creates an empty `MultiMap`.
+    pub fn new() -> Self {
+        Self::with_hasher(BuildDefaultHasher::default())
+    }
+}
+
+impl<K1, K2, V> Default for MultiMap<K1, K2, V>
+where
+    K1: Eq + Hash,
+    K2: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K1, K2, V, S> MultiMap<K1, K2, V, S>
+where
+    K1: Eq + Hash,
+    K2: Eq + Hash,
+    S: BuildHasher + Clone,
+{
+    /// Creates an empty `MultiMap` whose maps hash keys with hashers built
+    /// by `hasher`.
+    pub fn with_hasher(hasher: S) -> Self {
+        Self {
+            groups: HashMap::with_hasher(DEFAULT_CAPACITY, hasher.clone()),
+            len: 0,
+            build_hasher: hasher,
+        }
+    }
+
+    /// Inserts a value under `k1` and `k2`, creating the group of `k1` if
+    /// needed. Returns the previous value if the pair was present.
+    pub fn insert(&mut self, k1: K1, k2: K2, v: V) -> Option<V> {
+        let build_hasher = &self.build_hasher;
+        let group = self.groups.get_or_insert_with(k1, || {
+            HashMap::with_hasher(MIN_CAPACITY, build_hasher.clone())
+        });
+        let old = group.insert(k2, v);
+        if old.is_none() {
+            self.len += 1;
+        }
+        old
+    }
+
+    /// Returns a reference to the value under `k1` and `k2`.
+    pub fn get<Q1, Q2>(&self, k1: &Q1, k2: &Q2) -> Option<&V>
+    where
+        K1: Borrow<Q1>,
+        K2: Borrow<Q2>,
+        Q1: Eq + Hash + ?Sized,
+        Q2: Eq + Hash + ?Sized,
+    {
+        self.groups.get(k1)?.get(k2)
+    }
+
+    /// Returns a mutable reference to the value under `k1` and `k2`.
+    pub fn get_mut<Q1, Q2>(&mut self, k1: &Q1, k2: &Q2) -> Option<&mut V>
+    where
+        K1: Borrow<Q1>,
+        K2: Borrow<Q2>,
+        Q1: Eq + Hash + ?Sized,
+        Q2: Eq + Hash + ?Sized,
+    {
+        self.groups.get_mut(k1)?.get_mut(k2)
+    }
+
+    /// Returns the group of `k1`, if it holds any value.
+    pub fn group<Q1>(&self, k1: &Q1) -> Option<&HashMap<K2, V, S>>
+    where
+        K1: Borrow<Q1>,
+        Q1: Eq + Hash + ?Sized,
+    {
+        self.groups.get(k1)
+    }
+
+    /// Removes the value under `k1` and `k2`, returning it if it was
+    /// present. The group of `k1` is dropped once empty.
+    pub fn remove<Q1, Q2>(&mut self, k1: &Q1, k2: &Q2) -> Option<V>
+    where
+        K1: Borrow<Q1>,
+        K2: Borrow<Q2>,
+        Q1: Eq + Hash + ?Sized,
+        Q2: Eq + Hash + ?Sized,
+    {
+        let group = self.groups.get_mut(k1)?;
+        let v = group.remove(k2)?;
+        if group.is_empty() {
+            self.groups.remove(k1);
+        }
+        self.len -= 1;
+        Some(v)
+    }
+
+    /// Removes the whole group of `k1`, returning it if it was present.
+    pub fn remove_group<Q1>(&mut self, k1: &Q1) -> Option<HashMap<K2, V, S>>
+    where
+        K1: Borrow<Q1>,
+        Q1: Eq + Hash + ?Sized,
+    {
+        let group = self.groups.remove(k1)?;
+        self.len -= group.len();
+        Some(group)
+    }
+}
+
+impl<K1, K2, V, S> MultiMap<K1, K2, V, S> {
+    /// Returns the number of values in all groups.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the map holds no value.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of groups.
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Returns an iterator over all `(k1, k2, value)` triples, group by
+    /// group, in arbitrary order.
+    pub fn iter(&self) -> impl Iterator<Item = (&K1, &K2, &V)> {
+        self.groups
+            .iter()
+            .flat_map(|(k1, group)| group.iter().map(move |(k2, v)| (k1, k2, v)))
+    }
+
+    /// Removes all groups.
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.len = 0;
+    }
+}
+
+impl<K1, K2, V, S> fmt::Debug for MultiMap<K1, K2, V, S>
+where
+    K1: fmt::Debug,
+    K2: fmt::Debug,
+    V: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.groups.iter()).finish()
+    }
+}
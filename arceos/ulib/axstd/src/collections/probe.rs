@@ -0,0 +1,51 @@
+//! The probing shared by [`HashMap`] and [`ArrayHashMap`], which needs no
+//! heap allocation.
+//!
+//! [`HashMap`]: super::HashMap
+//! [`ArrayHashMap`]: super::ArrayHashMap
+
+/// A quadratic probe sequence, stepping by triangular numbers: `h`, `h + 1`,
+/// `h + 3`, `h + 6`, ... wrapping around the capacity.
+///
+/// With a power-of-two capacity, this visits every bucket exactly once.
+pub(super) struct ProbeSeq {
+    index: usize,
+    stride: usize,
+    remaining: usize,
+    capacity: usize,
+}
+
+impl ProbeSeq {
+    /// Starts at bucket `ideal` of `capacity`, a power of two.
+    pub(super) fn new(ideal: usize, capacity: usize) -> Self {
+        Self {
+            index: ideal,
+            stride: 0,
+            remaining: capacity,
+            capacity,
+        }
+    }
+}
+
+impl Iterator for ProbeSeq {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let index = self.index;
+        self.remaining -= 1;
+        self.stride += 1;
+        self.index = (index + self.stride) & (self.capacity - 1);
+        Some(index)
+    }
+}
+
+/// Returns the ideal bucket of a key hashing to `hash`, out of `capacity`,
+/// a power of two, by folding and masking as explained on `HashMap::hash`.
+pub(super) fn ideal_bucket(hash: u64, capacity: usize) -> usize {
+    let hash = hash ^ (hash >> 32);
+    let hash = hash ^ (hash >> 16);
+    hash as usize & (capacity - 1)
+}
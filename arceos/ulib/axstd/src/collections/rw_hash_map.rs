@@ -0,0 +1,304 @@
+//! A [`HashMap`] shared between threads behind a reader-writer lock.
+
+use core::borrow::Borrow;
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::hash::{BuildDefaultHasher, BuildHasher, Hash};
+use super::{HashMap, DEFAULT_CAPACITY};
+
+/// A reader-writer lock guarding no data, which [`RwHashMap`] is generic
+/// over so that the kernel can supply its own.
+///
+/// # Safety
+///
+/// Implementations must grant either any number of shared locks or a single
+/// exclusive lock at a time, and synchronize memory accordingly: releasing
+/// a lock must happen before acquiring it again.
+pub unsafe trait RawRwLock {
+    /// An unlocked lock.
+    const INIT: Self;
+
+    /// Acquires a shared lock, blocking while an exclusive one is held.
+    fn lock_shared(&self);
+
+    /// Releases a shared lock.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold a shared lock.
+    unsafe fn unlock_shared(&self);
+
+    /// Acquires the exclusive lock, blocking while any other lock is held.
+    fn lock_exclusive(&self);
+
+    /// Releases the exclusive lock.
+    ///
+    /// # Safety
+    ///
+    /// The caller must hold the exclusive lock.
+    unsafe fn unlock_exclusive(&self);
+}
+
+/// A spinning [`RawRwLock`], usable before the scheduler is up.
+///
+/// Writers go first: once one is waiting, new shared locks wait as well, so
+/// a steady stream of readers can't starve it. As a consequence, a thread
+/// holding a shared lock must not take another one, or it may wait on a
+/// writer that waits on it.
+pub struct SpinRwLock {
+    /// Number of readers, plus `WRITER` if the exclusive lock is held and
+    /// `PENDING` if a writer is waiting for it.
+    state: AtomicUsize,
+}
+
+const WRITER: usize = 1 << (usize::BITS - 1);
+const PENDING: usize = 1 << (usize::BITS - 2);
+const READERS: usize = PENDING - 1;
+
+unsafe impl RawRwLock for SpinRwLock {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const INIT: Self = Self {
+        state: AtomicUsize::new(0),
+    };
+
+    fn lock_shared(&self) {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & (WRITER | PENDING) != 0 || state & READERS == READERS {
+                core::hint::spin_loop();
+                state = self.state.load(Ordering::Relaxed);
+                continue;
+            }
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    unsafe fn unlock_shared(&self) {
+        self.state.fetch_sub(1, Ordering::Release);
+    }
+
+    fn lock_exclusive(&self) {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            if state & (WRITER | READERS) == 0 {
+                // This clears `PENDING`, which other waiting writers set
+                // again.
+                match self.state.compare_exchange_weak(
+                    state,
+                    WRITER,
+                    Ordering::Acquire,
+                    Ordering::Relaxed,
+                ) {
+                    Ok(_) => return,
+                    Err(current) => state = current,
+                }
+                continue;
+            }
+            if state & PENDING == 0 {
+                self.state.fetch_or(PENDING, Ordering::Relaxed);
+            }
+            core::hint::spin_loop();
+            state = self.state.load(Ordering::Relaxed);
+        }
+    }
+
+    unsafe fn unlock_exclusive(&self) {
+        self.state.fetch_and(!WRITER, Ordering::Release);
+    }
+}
+
+/// A [`HashMap`] readable from many threads at once, and writable from one
+/// at a time.
+///
+/// The map is guarded by a [`RawRwLock`] `L`, [`SpinRwLock`] by default.
+/// Lookups return a [`ReadGuard`] holding the shared lock, so writers wait
+/// until every value handed out has been dropped.
+pub struct RwHashMap<K, V, L = SpinRwLock, S = BuildDefaultHasher> {
+    lock: L,
+    map: UnsafeCell<HashMap<K, V, S>>,
+}
+
+// Readers on different threads share `&K` and `&V`, and a writer on any
+// thread may drop or move them.
+unsafe impl<K, V, L, S> Sync for RwHashMap<K, V, L, S>
+where
+    K: Send + Sync,
+    V: Send + Sync,
+    L: Sync,
+    S: Send + Sync,
+{
+}
+
+impl<K, V, L> RwHashMap<K, V, L>
+where
+    K: Eq + Hash,
+    L: RawRwLock,
+{
+    /// Creates an empty map with the default capacity.
+    pub fn new() -> Self {
+        Self::from_map(HashMap::new())
+    }
+}
+
+impl<K, V, L, S> Default for RwHashMap<K, V, L, S>
+where
+    K: Eq + Hash,
+    L: RawRwLock,
+    S: BuildHasher + Default,
+{
+    fn default() -> Self {
+        Self::from_map(HashMap::with_hasher(DEFAULT_CAPACITY, S::default()))
+    }
+}
+
+impl<K, V, L, S> RwHashMap<K, V, L, S>
+where
+    K: Eq + Hash,
+    L: RawRwLock,
+    S: BuildHasher,
+{
+    /// Wraps an existing map.
+    pub fn from_map(map: HashMap<K, V, S>) -> Self {
+        Self {
+            lock: L::INIT,
+            map: UnsafeCell::new(map),
+        }
+    }
+
+    /// Returns a reference to the value of the key, holding the shared lock
+    /// until it is dropped.
+    pub fn read_get<Q>(&self, k: &Q) -> Option<ReadGuard<'_, V, L>>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.lock.lock_shared();
+        // SAFETY: the shared lock keeps writers out.
+        match unsafe { &*self.map.get() }.get(k) {
+            Some(value) => Some(ReadGuard {
+                lock: &self.lock,
+                value,
+            }),
+            None => {
+                // SAFETY: locked above, and no guard was created.
+                unsafe { self.lock.unlock_shared() };
+                None
+            }
+        }
+    }
+
+    /// Runs `f` on the map under the shared lock.
+    pub fn read<R>(&self, f: impl FnOnce(&HashMap<K, V, S>) -> R) -> R {
+        self.lock.lock_shared();
+        let _unlock = Unlock(|| unsafe { self.lock.unlock_shared() });
+        // SAFETY: the shared lock keeps writers out.
+        f(unsafe { &*self.map.get() })
+    }
+
+    /// Runs `f` on the map under the exclusive lock.
+    pub fn write<R>(&self, f: impl FnOnce(&mut HashMap<K, V, S>) -> R) -> R {
+        self.lock.lock_exclusive();
+        let _unlock = Unlock(|| unsafe { self.lock.unlock_exclusive() });
+        // SAFETY: the exclusive lock keeps everyone else out.
+        f(unsafe { &mut *self.map.get() })
+    }
+
+    /// Inserts a key-value pair under the exclusive lock, returning the
+    /// previous value of the key if it was present.
+    pub fn write_insert(&self, k: K, v: V) -> Option<V> {
+        self.write(|map| map.insert(k, v))
+    }
+
+    /// Removes a key under the exclusive lock, returning its value if it was
+    /// present.
+    pub fn write_remove<Q>(&self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        self.write(|map| map.remove(k))
+    }
+
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.read(|map| map.len())
+    }
+
+    /// Returns `true` if the map contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.read(|map| map.is_empty())
+    }
+}
+
+impl<K, V, L, S> RwHashMap<K, V, L, S> {
+    /// Returns a mutable reference to the map, without locking since the
+    /// borrow is exclusive.
+    pub fn get_mut(&mut self) -> &mut HashMap<K, V, S> {
+        self.map.get_mut()
+    }
+
+    /// Unwraps the map.
+    pub fn into_inner(self) -> HashMap<K, V, S> {
+        self.map.into_inner()
+    }
+}
+
+impl<K, V, L, S> fmt::Debug for RwHashMap<K, V, L, S>
+where
+    K: fmt::Debug + Eq + Hash,
+    V: fmt::Debug,
+    L: RawRwLock,
+    S: BuildHasher,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.read(|map| map.fmt(f))
+    }
+}
+
+/// A value of an [`RwHashMap`], from [`RwHashMap::read_get`]. The shared
+/// lock is released when it is dropped.
+pub struct ReadGuard<'a, V, L: RawRwLock> {
+    lock: &'a L,
+    value: &'a V,
+}
+
+impl<V, L: RawRwLock> Deref for ReadGuard<'_, V, L> {
+    type Target = V;
+
+    fn deref(&self) -> &V {
+        self.value
+    }
+}
+
+impl<V, L: RawRwLock> Drop for ReadGuard<'_, V, L> {
+    fn drop(&mut self) {
+        // SAFETY: the guard was created holding a shared lock.
+        unsafe { self.lock.unlock_shared() };
+    }
+}
+
+impl<V: fmt::Debug, L: RawRwLock> fmt::Debug for ReadGuard<'_, V, L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+/// Runs its closure when dropped, so that locks are released on unwinding.
+struct Unlock<F: FnMut()>(F);
+
+impl<F: FnMut()> Drop for Unlock<F> {
+    fn drop(&mut self) {
+        (self.0)()
+    }
+}
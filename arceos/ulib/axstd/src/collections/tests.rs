@@ -0,0 +1,1872 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::Cell;
+
+use super::hash::{
+    default_hasher, BuildCrc32, BuildFxHasher, BuildHasher, BuildSipHasher13, Crc32Hasher,
+    FNV1aHasher, FxHasher, Hash, Hasher, SipHasher13, FNV_OFFSET_BASIS,
+};
+use super::{
+    next_capacity, ArrayHashMap, BloomFilter, Entry, EntryRef, HashMap, HashSet, MultiMap,
+    RawRwLock, RwHashMap, SpinRwLock, DEFAULT_CAPACITY, MIN_CAPACITY,
+};
+
+/// Returns the bucket `key` hashes to in a map with `capacity` buckets, the
+/// same way `HashMap` does.
+fn ideal_bucket<K: Hash + ?Sized>(key: &K, capacity: usize) -> usize {
+    let mut hasher = FNV1aHasher::default();
+    key.hash(&mut hasher);
+    super::probe::ideal_bucket(hasher.finish(), capacity)
+}
+
+/// Returns `n` distinct keys that all hash to the same bucket.
+fn colliding_keys(n: usize, capacity: usize) -> Vec<String> {
+    let target = ideal_bucket(&String::from("key_0"), capacity);
+    (0..)
+        .map(|i| format!("key_{i}"))
+        .filter(|key| ideal_bucket(key, capacity) == target)
+        .take(n)
+        .collect()
+}
+
+#[test]
+fn insert_and_get() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    assert_eq!(map.insert(String::from("a"), 1), None);
+    assert_eq!(map.insert(String::from("b"), 2), None);
+    assert_eq!(map.insert(String::from("a"), 3), Some(1));
+    assert_eq!(map.get(&String::from("a")), Some(&3));
+    assert_eq!(map.get(&String::from("b")), Some(&2));
+    assert_eq!(map.get(&String::from("c")), None);
+    assert_eq!(map.iter().count(), 2);
+}
+
+#[test]
+fn remove_keeps_probe_chain() {
+    let keys = colliding_keys(3, 8);
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for (i, key) in keys.iter().enumerate() {
+        map.insert(key.clone(), i);
+    }
+
+    assert_eq!(map.remove(&keys[1]), Some(1));
+    assert_eq!(map.remove(&keys[1]), None);
+    assert_eq!(map.get(&keys[0]), Some(&0));
+    assert_eq!(map.get(&keys[1]), None);
+    assert_eq!(map.get(&keys[2]), Some(&2));
+
+    // The tombstone is reused instead of appending to the chain.
+    map.insert(keys[1].clone(), 10);
+    assert_eq!(map.get(&keys[1]), Some(&10));
+    assert_eq!(map.get(&keys[2]), Some(&2));
+    assert_eq!(map.iter().count(), 3);
+}
+
+#[test]
+fn insert_reuses_tombstones_when_full() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    for i in 0..4 {
+        map.insert(format!("key_{i}"), i);
+    }
+    assert_eq!(map.remove(&String::from("key_2")), Some(2));
+    assert_eq!(map.insert(String::from("key_9"), 9), None);
+    assert_eq!(map.get(&String::from("key_9")), Some(&9));
+    for i in [0, 1, 3] {
+        assert_eq!(map.get(&format!("key_{i}")), Some(&i));
+    }
+}
+
+#[test]
+fn insert_grows_past_load_factor() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    for i in 0..1000 {
+        assert_eq!(map.insert(format!("key_{i}"), i), None);
+    }
+    for i in 0..1000 {
+        assert_eq!(map.get(&format!("key_{i}")), Some(&i));
+    }
+    assert_eq!(map.iter().count(), 1000);
+
+    let mut map = HashMap::new_with_capacity(Some(0));
+    assert_eq!(map.get(&String::from("a")), None);
+    map.insert(String::from("a"), 1);
+    assert_eq!(map.get(&String::from("a")), Some(&1));
+}
+
+#[test]
+fn tombstone_churn_does_not_fill_map() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for i in 0..1000 {
+        map.insert(format!("key_{i}"), i);
+        assert_eq!(map.remove(&format!("key_{i}")), Some(i));
+    }
+    map.insert(String::from("last"), 0);
+    assert_eq!(map.get(&String::from("last")), Some(&0));
+    assert_eq!(map.iter().count(), 1);
+}
+
+#[test]
+fn len_tracks_fresh_inserts_and_removals() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    assert!(map.is_empty());
+    assert_eq!(map.len(), 0);
+
+    map.insert(String::from("a"), 1);
+    map.insert(String::from("b"), 2);
+    assert_eq!(map.len(), 2);
+    map.insert(String::from("a"), 3);
+    assert_eq!(map.len(), 2);
+
+    map.remove(&String::from("a"));
+    assert_eq!(map.len(), 1);
+    map.remove(&String::from("a"));
+    assert_eq!(map.len(), 1);
+    map.remove(&String::from("b"));
+    assert!(map.is_empty());
+}
+
+#[test]
+fn contains_key_follows_probe_chain() {
+    let keys = colliding_keys(3, 8);
+    let mut map = HashMap::new_with_capacity(Some(8));
+    map.insert(keys[0].clone(), 0);
+    map.insert(keys[1].clone(), 1);
+
+    assert!(map.contains_key(&keys[0]));
+    // Sits one bucket past its ideal one, behind the collision.
+    assert!(map.contains_key(&keys[1]));
+    assert!(!map.contains_key(&keys[2]));
+    assert!(!map.contains_key(&String::from("absent")));
+
+    map.remove(&keys[0]);
+    assert!(!map.contains_key(&keys[0]));
+    assert!(map.contains_key(&keys[1]));
+}
+
+#[test]
+fn get_mut_updates_in_place() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    map.insert(String::from("a"), 1);
+    *map.get_mut(&String::from("a")).unwrap() += 10;
+    assert_eq!(map.get(&String::from("a")), Some(&11));
+    assert_eq!(map.get_mut(&String::from("b")), None);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn clear_empties_and_allows_reuse() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    for i in 0..10 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.remove(&String::from("key_3"));
+    map.clear();
+    assert_eq!(map.len(), 0);
+    assert_eq!(map.iter().count(), 0);
+    for i in 0..10 {
+        assert_eq!(map.get(&format!("key_{i}")), None);
+    }
+
+    map.insert(String::from("key_3"), 30);
+    assert_eq!(map.get(&String::from("key_3")), Some(&30));
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn keys_and_values() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    for i in 0..5 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.remove(&String::from("key_2"));
+
+    let mut keys: Vec<_> = map.keys().cloned().collect();
+    keys.sort();
+    assert_eq!(keys, ["key_0", "key_1", "key_3", "key_4"]);
+
+    for value in map.values_mut() {
+        *value *= 10;
+    }
+    let mut values: Vec<_> = map.values().copied().collect();
+    values.sort();
+    assert_eq!(values, [0, 10, 30, 40]);
+    assert_eq!(map.get(&String::from("key_4")), Some(&40));
+}
+
+#[test]
+fn into_iter_moves_entries_out() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    for i in 0..5 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.remove(&String::from("key_0"));
+
+    let mut pairs = Vec::new();
+    for (k, v) in map {
+        pairs.push((k, v));
+    }
+    pairs.sort();
+    let expected: Vec<_> = (1..5).map(|i| (format!("key_{i}"), i)).collect();
+    assert_eq!(pairs, expected);
+}
+
+#[test]
+fn entry_api() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    assert_eq!(*map.entry(String::from("a")).or_insert(1), 1);
+    assert_eq!(*map.entry(String::from("a")).or_insert(2), 1);
+    assert_eq!(map.len(), 1);
+
+    map.entry(String::from("a"))
+        .and_modify(|v| *v += 5)
+        .or_insert(0);
+    map.entry(String::from("b"))
+        .and_modify(|v| *v += 5)
+        .or_insert(0);
+    assert_eq!(map.get(&String::from("a")), Some(&6));
+    assert_eq!(map.get(&String::from("b")), Some(&0));
+
+    let mut called = false;
+    map.entry(String::from("a")).or_insert_with(|| {
+        called = true;
+        0
+    });
+    assert!(!called);
+    *map.entry(String::from("c")).or_insert_with(|| 7) += 1;
+    assert_eq!(map.get(&String::from("c")), Some(&8));
+
+    match map.entry(String::from("b")) {
+        Entry::Occupied(entry) => assert_eq!(entry.remove(), 0),
+        Entry::Vacant(_) => panic!("entry should be occupied"),
+    }
+    assert!(!map.contains_key(&String::from("b")));
+    assert_eq!(map.len(), 2);
+
+    // Inserting through entries grows the map like `insert` does.
+    for i in 0..100 {
+        map.entry(format!("key_{i}")).or_insert(i);
+    }
+    assert_eq!(map.len(), 102);
+    assert_eq!(map.get(&String::from("key_99")), Some(&99));
+}
+
+#[test]
+fn collect_and_extend() {
+    let pairs: Vec<_> = (0..100).map(|i| (format!("key_{i}"), i)).collect();
+    let mut map: HashMap<_, _> = pairs.into_iter().collect();
+    assert_eq!(map.len(), 100);
+
+    map.extend((50..150).map(|i| (format!("key_{i}"), i * 2)));
+    assert_eq!(map.len(), 150);
+    assert_eq!(map.get(&String::from("key_10")), Some(&10));
+    assert_eq!(map.get(&String::from("key_60")), Some(&120));
+    assert_eq!(map.get(&String::from("key_149")), Some(&298));
+}
+
+#[test]
+fn values_need_not_be_eq_or_clone() {
+    use alloc::boxed::Box;
+
+    let mut map: HashMap<String, Box<dyn Fn(u32) -> u32>> = HashMap::new_with_capacity(Some(4));
+    map.insert(String::from("double"), Box::new(|x| x * 2));
+    map.insert(String::from("square"), Box::new(|x| x * x));
+    for i in 0..10 {
+        map.insert(format!("id_{i}"), Box::new(|x| x));
+    }
+    assert_eq!(map.get(&String::from("double")).unwrap()(3), 6);
+    assert_eq!(map.get(&String::from("square")).unwrap()(3), 9);
+    assert_eq!(map.get(&String::from("id_7")).unwrap()(3), 3);
+}
+
+#[test]
+fn keys_need_not_be_clone() {
+    #[derive(PartialEq, Eq)]
+    struct Name(String);
+
+    impl Hash for Name {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            self.0.hash(state);
+        }
+    }
+
+    let mut map = HashMap::new_with_capacity(Some(2));
+    for i in 0..10 {
+        map.insert(Name(format!("key_{i}")), i);
+    }
+    assert_eq!(map.get(&Name(String::from("key_4"))), Some(&4));
+    assert_eq!(map.remove(&Name(String::from("key_4"))), Some(4));
+    assert_eq!(map.keys().count(), 9);
+}
+
+/// Hashes everything to zero, counting the bytes written.
+struct CountingHasher<'a>(&'a Cell<usize>);
+
+impl Hasher for CountingHasher<'_> {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.set(self.0.get() + bytes.len());
+    }
+}
+
+struct BuildCounting<'a>(&'a Cell<usize>);
+
+impl<'a> BuildHasher for BuildCounting<'a> {
+    type Hasher = CountingHasher<'a>;
+
+    fn build_hasher(&self) -> CountingHasher<'a> {
+        CountingHasher(self.0)
+    }
+}
+
+#[test]
+fn custom_build_hasher() {
+    let written = Cell::new(0);
+    let mut map = HashMap::with_hasher(8, BuildCounting(&written));
+    // Strings write their bytes, then a terminator byte.
+    map.insert(String::from("abc"), 1);
+    assert_eq!(written.get(), 4);
+    map.insert(String::from("de"), 2);
+    assert_eq!(map.get(&String::from("abc")), Some(&1));
+    assert_eq!(written.get(), 11);
+
+    // Every key collides, but probing still tells them apart.
+    for i in 0..20 {
+        map.insert(format!("key_{i}"), i);
+    }
+    assert_eq!(map.len(), 22);
+    assert_eq!(map.get(&String::from("key_13")), Some(&13));
+    assert_eq!(map.get(&String::from("de")), Some(&2));
+}
+
+#[test]
+fn siphash13_test_vectors() {
+    // Key 00..0f, messages 00..(n-1), from the reference implementation.
+    const VECTORS: [(usize, u64); 6] = [
+        (0, 0xabac_0158_050f_c4dc),
+        (1, 0xc9f4_9bf3_7d57_ca93),
+        (7, 0xd392_7d98_9bb1_1140),
+        (8, 0x3690_9511_8d29_9a8e),
+        (15, 0xd320_d86d_2a51_9956),
+        (63, 0x9d19_9062_b7bb_b3a8),
+    ];
+    let k0 = u64::from_le_bytes([0, 1, 2, 3, 4, 5, 6, 7]);
+    let k1 = u64::from_le_bytes([8, 9, 10, 11, 12, 13, 14, 15]);
+    let input: Vec<u8> = (0..64).collect();
+    for (len, expected) in VECTORS {
+        let mut hasher = SipHasher13::new_with_keys(k0, k1);
+        hasher.write(&input[..len]);
+        assert_eq!(hasher.finish(), expected, "length {len}");
+
+        // Feeding the bytes one by one gives the same result.
+        let mut hasher = SipHasher13::new_with_keys(k0, k1);
+        for byte in &input[..len] {
+            hasher.write_u8(*byte);
+        }
+        assert_eq!(hasher.finish(), expected, "length {len}");
+    }
+}
+
+#[test]
+fn siphash13_map() {
+    let state = BuildSipHasher13::new(1, 2);
+    assert_ne!(
+        state.hash_one("key"),
+        BuildSipHasher13::new(3, 4).hash_one("key")
+    );
+
+    let mut map = HashMap::with_hasher(8, state);
+    for i in 0..100 {
+        map.insert(format!("key_{i}"), i);
+    }
+    assert_eq!(map.get(&String::from("key_42")), Some(&42));
+    assert_eq!(map.len(), 100);
+}
+
+#[test]
+fn reset_hasher_matches_fresh_one() {
+    let mut fresh = default_hasher();
+    fresh.write(b"hello");
+    let mut reused = default_hasher();
+    reused.write(b"something else");
+    reused.reset();
+    reused.write(b"hello");
+    assert_eq!(reused.finish(), fresh.finish());
+
+    let mut fresh = SipHasher13::new_with_keys(1, 2);
+    fresh.write(b"hello");
+    let mut reused = SipHasher13::new_with_keys(1, 2);
+    reused.write(b"something else");
+    reused.reset();
+    reused.write(b"hello");
+    assert_eq!(reused.finish(), fresh.finish());
+}
+
+#[test]
+fn primitive_keys() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for i in 0..100u32 {
+        map.insert(i, i * 2);
+    }
+    assert_eq!(map.get(&42), Some(&84));
+    assert_eq!(map.get(&100), None);
+
+    let mut map = HashMap::new_with_capacity(Some(8));
+    map.insert(-1i64, "minus one");
+    map.insert(i64::MAX, "max");
+    assert_eq!(map.get(&-1), Some(&"minus one"));
+    assert_eq!(map.get(&i64::MAX), Some(&"max"));
+
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for c in 'a'..='z' {
+        map.insert(c, c as u32);
+    }
+    map.insert('✓', 0);
+    assert_eq!(map.get(&'q'), Some(&('q' as u32)));
+    assert_eq!(map.get(&'✓'), Some(&0));
+
+    let mut map = HashMap::new_with_capacity(Some(2));
+    map.insert(true, 1);
+    map.insert(false, 0);
+    assert_eq!(map.get(&true), Some(&1));
+    assert_eq!(map.get(&false), Some(&0));
+
+    // `char` is hashed as its `u32` code point.
+    let mut by_char = FNV1aHasher::default();
+    'x'.hash(&mut by_char);
+    let mut by_u32 = FNV1aHasher::default();
+    by_u32.write_u32('x' as u32);
+    assert_eq!(by_char.finish(), by_u32.finish());
+}
+
+fn fnv_hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = FNV1aHasher::default();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn tuple_keys() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for x in -5..5i32 {
+        for y in -5..5i32 {
+            map.insert((x, y), x * 100 + y);
+        }
+    }
+    assert_eq!(map.len(), 100);
+    assert_eq!(map.get(&(3, -2)), Some(&298));
+    assert_eq!(map.get(&(-2, 3)), Some(&-197));
+    assert_eq!(map.get(&(5, 5)), None);
+
+    assert_ne!(fnv_hash(&(1u32, 2u32)), fnv_hash(&(2u32, 1u32)));
+    assert_eq!(fnv_hash(&(7u8,)), fnv_hash(&7u8));
+    let wide = (
+        1u8, 2u16, 3u32, 4u64, 5u128, 6usize, 7i8, 8i16, 9i32, 10i64, 'k', true,
+    );
+    assert_eq!(fnv_hash(&wide), fnv_hash(&wide));
+}
+
+#[test]
+fn sequence_keys() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    map.insert(vec![1u8, 2, 3], "a");
+    map.insert(vec![1u8, 2], "b");
+    map.insert(vec![], "empty");
+    assert_eq!(map.get(&vec![1, 2, 3]), Some(&"a"));
+    assert_eq!(map.get(&vec![1, 2]), Some(&"b"));
+    assert_eq!(map.get(&vec![]), Some(&"empty"));
+    assert_eq!(map.get(&vec![1]), None);
+
+    let mut map = HashMap::new_with_capacity(Some(8));
+    let mac = [0x52u8, 0x54, 0x00, 0x12, 0x34, 0x56];
+    map.insert(mac, "eth0");
+    assert_eq!(map.get(&mac), Some(&"eth0"));
+    assert_eq!(map.get(&[0; 6]), None);
+
+    // Arrays, slices and vectors of the same items hash alike.
+    assert_eq!(fnv_hash(&mac), fnv_hash(&mac.to_vec()));
+    // The length prefix keeps element boundaries apart.
+    assert_ne!(
+        fnv_hash(&(vec![1u8, 2], Vec::<u8>::new())),
+        fnv_hash(&(vec![1u8], vec![2u8]))
+    );
+}
+
+#[test]
+fn option_and_reference_keys() {
+    assert_ne!(fnv_hash(&Some(5u32)), fnv_hash(&None::<u32>));
+    assert_ne!(fnv_hash(&Some(0u8)), fnv_hash(&None::<u8>));
+    assert_eq!(fnv_hash(&&5u32), fnv_hash(&5u32));
+    assert_eq!(fnv_hash(&"abc"), fnv_hash(&String::from("abc")));
+
+    let mut map = HashMap::new_with_capacity(Some(4));
+    map.insert(Some(5u32), "five");
+    map.insert(None, "none");
+    assert_eq!(map.get(&Some(5)), Some(&"five"));
+    assert_eq!(map.get(&None), Some(&"none"));
+    assert_eq!(map.get(&Some(6)), None);
+}
+
+#[test]
+#[cfg(target_pointer_width = "64")]
+fn fxhash_is_deterministic() {
+    let mut hasher = FxHasher::default();
+    hasher.write(b"hello world, this is fx");
+    assert_eq!(hasher.finish(), 0x994e_a6a0_3800_8ab1);
+    assert_eq!(BuildFxHasher.hash_one(42usize), 0x5e77_c80c_6b95_bc72);
+
+    let mut map = HashMap::with_hasher(8, BuildFxHasher);
+    for i in 0..100 {
+        map.insert(format!("/usr/lib/module_{i}.ko"), i);
+    }
+    assert_eq!(map.get(&String::from("/usr/lib/module_77.ko")), Some(&77));
+}
+
+#[test]
+fn fxhash_long_input() {
+    let data: Vec<u8> = (0..1 << 20).map(|i| (i * 31 % 251) as u8).collect();
+    let mut fx = FxHasher::default();
+    fx.write(&data);
+
+    let mut again = FxHasher::default();
+    again.write(&data);
+    assert_eq!(fx.finish(), again.finish());
+    let mut streamed = FxHasher::default();
+    for chunk in data.chunks(4093) {
+        streamed.write(chunk);
+    }
+    assert_eq!(fx.finish(), streamed.finish());
+    let mut flipped = data.clone();
+    flipped[1 << 19] ^= 1;
+    let mut other = FxHasher::default();
+    other.write(&flipped);
+    assert_ne!(fx.finish(), other.finish());
+}
+
+/// Hashes a list of chunks with some hasher.
+type HashChunks = fn(&[&[u8]]) -> u64;
+
+/// Hashes `chunks` with one `write` call each.
+fn hash_chunks<H: Hasher>(mut hasher: H, chunks: &[&[u8]]) -> u64 {
+    for chunk in chunks {
+        hasher.write(chunk);
+    }
+    hasher.finish()
+}
+
+#[test]
+fn chunking_does_not_change_hashes() {
+    let hashers: [(&str, HashChunks); 4] = [
+        ("fnv", |c| hash_chunks(FNV1aHasher::default(), c)),
+        ("sip", |c| hash_chunks(SipHasher13::new_with_keys(1, 2), c)),
+        ("fx", |c| hash_chunks(FxHasher::default(), c)),
+        ("crc32", |c| hash_chunks(Crc32Hasher::default(), c)),
+    ];
+    for len in [0, 1, 3, 7, 8, 9, 15, 16, 17, 31, 64, 100] {
+        let data: Vec<u8> = (0..len).map(|i| (i * 73 % 256) as u8 ^ 0x5a).collect();
+        for (name, hash) in hashers {
+            let whole = hash(&[&data]);
+            for i in 0..=len {
+                let (a, b) = data.split_at(i);
+                assert_eq!(hash(&[a, b]), whole, "{name}: {len} bytes split at {i}");
+                for j in i..=len.min(i + 9) {
+                    let (b, c) = data[i..].split_at(j - i);
+                    assert_eq!(hash(&[a, b, c]), whole, "{name}: {len} bytes at {i}, {j}");
+                }
+            }
+            let bytes: Vec<&[u8]> = data.chunks(1).collect();
+            assert_eq!(hash(&bytes), whole, "{name}: {len} single bytes");
+        }
+    }
+
+    // Integers written directly still come after the pending bytes.
+    let mut split = FxHasher::default();
+    split.write(b"abc");
+    split.write(b"de");
+    split.write_u32(7);
+    let mut whole = FxHasher::default();
+    whole.write(b"abcde");
+    whole.write_u32(7);
+    assert_eq!(split.finish(), whole.finish());
+}
+
+#[test]
+fn float_bits_hashing() {
+    fn write_f64<H: Hasher>(mut state: H, f: f64) {
+        state.write_f64(f);
+    }
+
+    fn hash_f64(f: f64) -> u64 {
+        let mut hasher = FNV1aHasher::default();
+        // Goes through the `&mut H` forwarding impl.
+        write_f64(&mut hasher, f);
+        hasher.finish()
+    }
+
+    assert_eq!(hash_f64(1.5), hash_f64(3.0 / 2.0));
+    assert_ne!(hash_f64(1.5), hash_f64(2.5));
+    assert_ne!(hash_f64(0.0), hash_f64(-0.0));
+
+    let mut by_f32 = FNV1aHasher::default();
+    by_f32.write_f32(1.5);
+    let mut by_bits = FNV1aHasher::default();
+    by_bits.write_u32(1.5f32.to_bits());
+    assert_eq!(by_f32.finish(), by_bits.finish());
+}
+
+#[test]
+fn clone_is_independent() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for i in 0..20 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.remove(&String::from("key_5"));
+    let snapshot = map.clone();
+
+    map.insert(String::from("key_0"), 100);
+    map.insert(String::from("new"), 1);
+    map.remove(&String::from("key_1"));
+
+    assert_eq!(snapshot.len(), 19);
+    assert_eq!(snapshot.get(&String::from("key_0")), Some(&0));
+    assert_eq!(snapshot.get(&String::from("key_1")), Some(&1));
+    assert_eq!(snapshot.get(&String::from("key_5")), None);
+    assert_eq!(snapshot.get(&String::from("new")), None);
+    for i in 6..20 {
+        assert_eq!(snapshot.get(&format!("key_{i}")), Some(&i));
+    }
+}
+
+#[test]
+fn equality_ignores_layout() {
+    let mut a = HashMap::new_with_capacity(Some(4));
+    let mut b = HashMap::new_with_capacity(Some(64));
+    for i in 0..10 {
+        a.insert(format!("key_{i}"), i);
+        b.insert(format!("key_{}", 9 - i), 9 - i);
+    }
+    b.insert(String::from("extra"), 0);
+    assert!(a != b);
+    b.remove(&String::from("extra"));
+    assert!(a == b);
+
+    b.insert(String::from("key_3"), 30);
+    assert!(a != b);
+}
+
+#[test]
+fn debug_lists_entries() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    assert_eq!(format!("{map:?}"), "{}");
+    map.insert(String::from("a\"b"), 1);
+    map.insert(String::from("c"), 2);
+    let text = format!("{map:?}");
+    assert!(text.starts_with('{') && text.ends_with('}'));
+    assert!(text.contains(r#""a\"b": 1"#));
+    assert!(text.contains(r#""c": 2"#));
+}
+
+#[test]
+fn reserve_grows_once() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    assert_eq!(map.capacity(), 16);
+    map.reserve(12);
+    assert_eq!(map.capacity(), 16);
+
+    map.reserve(10_000);
+    let capacity = map.capacity();
+    assert!(capacity * 3 / 4 >= 10_000);
+    for i in 0..10_000 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.capacity(), capacity);
+    assert_eq!(map.get(&9_999), Some(&9_999));
+
+    // `new_with_capacity` sizes are kept as a floor.
+    let mut map = HashMap::new_with_capacity(Some(128));
+    for i in 0..96 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.capacity(), 128);
+    map.insert(96, 96);
+    assert_eq!(map.capacity(), 256);
+}
+
+#[test]
+fn capacity_is_rounded_to_power_of_two() {
+    assert_eq!(
+        HashMap::<u32, u32>::new_with_capacity(Some(100)).capacity(),
+        128
+    );
+    assert_eq!(
+        HashMap::<u32, u32>::new_with_capacity(Some(64)).capacity(),
+        64
+    );
+    assert_eq!(
+        HashMap::<u32, u32>::new_with_capacity(Some(1000)).capacity(),
+        1024
+    );
+    assert_eq!(
+        HashMap::<u32, u32>::new_with_capacity(Some(0)).capacity(),
+        MIN_CAPACITY
+    );
+    assert_eq!(next_capacity(0), MIN_CAPACITY);
+    assert_eq!(next_capacity(MIN_CAPACITY + 1), 2 * MIN_CAPACITY);
+    assert_eq!(next_capacity(1 << 20), 1 << 20);
+}
+
+#[test]
+fn quadratic_probing_survives_full_collisions() {
+    // Every key hashes to bucket 0, so each probe chain covers the whole
+    // table: quadratic probing must still reach every bucket.
+    let written = Cell::new(0);
+    let mut map = HashMap::with_hasher(16, BuildCounting(&written));
+    for i in 0..12u32 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.capacity(), 16);
+    for i in 0..12 {
+        assert_eq!(map.get(&i), Some(&i));
+    }
+    for i in (0..12).step_by(3) {
+        assert_eq!(map.remove(&i), Some(i));
+    }
+    for i in 0..12 {
+        assert_eq!(map.get(&i), (i % 3 != 0).then_some(&i));
+    }
+    for i in 12..200 {
+        map.insert(i, i);
+    }
+    assert!((12..200).all(|i| map.get(&i) == Some(&i)));
+}
+
+#[test]
+fn iter_is_exact_size() {
+    let mut map = HashMap::new_with_capacity(Some(64));
+    for i in 0..10u32 {
+        map.insert(i, i);
+    }
+    map.remove(&4);
+    let mut iter = map.iter();
+    assert_eq!(iter.len(), map.len());
+    for remaining in (0..9).rev() {
+        iter.next().unwrap();
+        assert_eq!(iter.len(), remaining);
+        assert_eq!(iter.size_hint(), (remaining, Some(remaining)));
+    }
+    assert!(iter.next().is_none());
+    assert_eq!(map.keys().len(), 9);
+    assert_eq!(map.values().len(), 9);
+}
+
+#[test]
+fn retain_even_values() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for i in 0..100u32 {
+        map.insert(format!("key_{i}"), i);
+    }
+    let mut visited = 0;
+    map.retain(|_, v| {
+        visited += 1;
+        *v += 1;
+        *v % 2 == 1
+    });
+    assert_eq!(visited, 100);
+    assert_eq!(map.len(), 50);
+    for i in 0..100u32 {
+        let expected = i + 1;
+        let found = map.get(&format!("key_{i}"));
+        assert_eq!(found, (i % 2 == 0).then_some(&expected));
+    }
+}
+
+#[test]
+fn retain_keys_in_set() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for i in 0..100u32 {
+        map.insert(i, format!("value_{i}"));
+    }
+    let keep: HashSet<u32> = (0..100).filter(|i| i % 3 == 0).collect();
+    map.retain_keys(|k| keep.contains(k));
+    assert_eq!(map.len(), keep.len());
+    map.assert_entries_consistent();
+    for i in 0..100u32 {
+        let expected = format!("value_{i}");
+        assert_eq!(map.get(&i), keep.contains(&i).then_some(&expected));
+    }
+
+    // Removed keys can come back, and kept ones are still updated in place.
+    assert_eq!(map.insert(1, String::from("back")), None);
+    assert_eq!(
+        map.insert(3, String::from("new")).as_deref(),
+        Some("value_3")
+    );
+    assert_eq!(map.len(), keep.len() + 1);
+    assert_eq!(map.get(&1).map(String::as_str), Some("back"));
+    map.retain_keys(|_| false);
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+}
+
+#[test]
+fn iter_ordered_follows_insertion_order() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    map.track_insertion_order();
+    let names = [
+        "init",
+        "kthreadd",
+        "ksoftirqd",
+        "rcu_sched",
+        "migration",
+        "idle",
+    ];
+    for (pid, name) in names.iter().enumerate() {
+        map.insert(*name, pid);
+    }
+    // Updates keep their place, and the map grows without losing the order.
+    map.insert("init", 100);
+    let mut other = HashMap::new();
+    other.insert("kthreadd", 1);
+    map.merge_with(other, |_, a, b| a + b);
+    for i in 0..20 {
+        map.insert(["tmp0", "tmp1", "tmp2", "tmp3", "tmp4"][i % 5], i);
+    }
+    assert!(map.capacity() > 8);
+    for tmp in ["tmp0", "tmp1", "tmp2", "tmp3", "tmp4"] {
+        map.remove(tmp);
+    }
+    assert_eq!(map.remove("ksoftirqd"), Some(2));
+    map.assert_entries_consistent();
+    let ordered: Vec<_> = map.iter_ordered().map(|(&k, &v)| (k, v)).collect();
+    assert_eq!(
+        ordered,
+        [
+            ("init", 100),
+            ("kthreadd", 2),
+            ("rcu_sched", 3),
+            ("migration", 4),
+            ("idle", 5)
+        ]
+    );
+
+    // A key inserted again goes last.
+    map.insert("ksoftirqd", 6);
+    map.retain_keys(|k| *k != "init");
+    let keys: Vec<_> = map.iter_ordered().map(|(&k, _)| k).collect();
+    assert_eq!(
+        keys,
+        ["kthreadd", "rcu_sched", "migration", "idle", "ksoftirqd"]
+    );
+    map.assert_entries_consistent();
+    map.clear();
+    assert_eq!(map.iter_ordered().count(), 0);
+    map.insert("init", 0);
+    assert_eq!(map.iter_ordered().count(), 1);
+}
+
+#[test]
+#[should_panic(expected = "capacity overflow")]
+fn reserve_panics_on_overflowing_length() {
+    let mut map = HashMap::new();
+    map.insert(1u32, 1u32);
+    map.reserve(usize::MAX);
+}
+
+#[test]
+#[should_panic(expected = "capacity overflow")]
+fn reserve_panics_on_overflowing_capacity() {
+    let mut map: HashMap<u32, u32> = HashMap::new();
+    map.reserve(usize::MAX / 2);
+}
+
+#[test]
+#[should_panic(expected = "insertion order is not tracked")]
+fn iter_ordered_requires_tracking() {
+    let map: HashMap<u32, u32> = HashMap::new();
+    map.iter_ordered().count();
+}
+
+#[test]
+fn sparse_map_iterators_yield_every_entry() {
+    let mut map = HashMap::new_with_capacity(Some(1 << 12));
+    for i in 0..6u32 {
+        map.insert(i, i * 10);
+    }
+    map.remove(&2);
+
+    let values = map.values_mut();
+    assert_eq!(values.len(), 5);
+    values.for_each(|v| *v += 1);
+    let mut values: Vec<_> = map.values().copied().collect();
+    values.sort_unstable();
+    assert_eq!(values, [1, 11, 31, 41, 51]);
+
+    let mut drain = map.clone().drain().collect::<Vec<_>>();
+    drain.sort_unstable();
+    let into_iter = map.clone().into_iter();
+    assert_eq!(into_iter.len(), 5);
+    let mut pairs: Vec<_> = into_iter.collect();
+    pairs.sort_unstable();
+    assert_eq!(pairs, [(0, 1), (1, 11), (3, 31), (4, 41), (5, 51)]);
+    assert_eq!(drain, pairs);
+
+    let mut drain = map.drain();
+    assert_eq!(drain.len(), 5);
+    drain.next();
+    assert_eq!(drain.len(), 4);
+    drop(drain);
+    assert!(map.is_empty());
+    map.assert_entries_consistent();
+    for i in 0..6u32 {
+        assert_eq!(map.get(&i), None);
+    }
+    map.insert(2, 20);
+    assert_eq!(map.get(&2), Some(&20));
+    map.assert_entries_consistent();
+}
+
+#[test]
+fn drain_empties_map() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    for i in 0..10u32 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.remove(&String::from("key_0"));
+    let capacity = map.capacity();
+
+    let mut pairs: Vec<_> = map.drain().collect();
+    pairs.sort();
+    let expected: Vec<_> = (1..10).map(|i| (format!("key_{i}"), i)).collect();
+    assert_eq!(pairs, expected);
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+    assert_eq!(map.capacity(), capacity);
+
+    // Dropping the iterator early still empties the map.
+    for i in 0..10u32 {
+        map.insert(format!("key_{i}"), i);
+    }
+    assert_eq!(map.drain().take(3).count(), 3);
+    assert!(map.is_empty());
+    assert_eq!(map.iter().count(), 0);
+    assert_eq!(map.get(&String::from("key_5")), None);
+    map.insert(String::from("key_5"), 5);
+    assert_eq!(map.get(&String::from("key_5")), Some(&5));
+
+    // Leaking the iterator leaks the entries, but leaves the map usable.
+    for i in 0..10u32 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.remove(&String::from("key_1"));
+    core::mem::forget(map.drain());
+    assert!(map.is_empty());
+    map.assert_entries_consistent();
+    for i in 0..10u32 {
+        assert_eq!(map.insert(format!("key_{i}"), i), None);
+    }
+    assert_eq!(map.remove(&String::from("key_3")), Some(3));
+    assert_eq!(map.len(), 9);
+    map.assert_entries_consistent();
+}
+
+#[test]
+fn index_present_key() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    map.insert(String::from("a"), 1);
+    assert_eq!(map[&String::from("a")], 1);
+}
+
+#[test]
+#[should_panic(expected = "no entry found for key")]
+fn index_missing_key() {
+    let map: HashMap<String, u32> = HashMap::new_with_capacity(Some(4));
+    let _ = map[&String::from("a")];
+}
+
+#[test]
+fn get_key_value_returns_stored_key() {
+    /// A name compared and hashed case-insensitively.
+    struct Name(&'static str);
+
+    impl PartialEq for Name {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.eq_ignore_ascii_case(other.0)
+        }
+    }
+
+    impl Eq for Name {}
+
+    impl Hash for Name {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            for byte in self.0.bytes() {
+                state.write_u8(byte.to_ascii_lowercase());
+            }
+        }
+    }
+
+    let mut map = HashMap::new_with_capacity(Some(4));
+    map.insert(Name("Content-Type"), 1);
+    let (key, value) = map.get_key_value(&Name("content-type")).unwrap();
+    assert_eq!(key.0, "Content-Type");
+    assert_eq!(*value, 1);
+    assert!(map.get_key_value(&Name("content-length")).is_none());
+}
+
+#[test]
+fn try_insert_never_overwrites() {
+    let mut map = HashMap::new_with_capacity(Some(4));
+    let value = map.try_insert(String::from("a"), 1).unwrap();
+    *value += 1;
+    assert_eq!(map.get(&String::from("a")), Some(&2));
+
+    let err = map.try_insert(String::from("a"), 10).unwrap_err();
+    assert_eq!(err.value, 10);
+    assert_eq!(err.entry.get(), &2);
+    assert_eq!(
+        format!("{err}"),
+        r#"failed to insert 10, key "a" already exists with value 2"#
+    );
+    assert_eq!(map.get(&String::from("a")), Some(&2));
+    assert_eq!(map.len(), 1);
+}
+
+fn sorted<'a>(iter: impl Iterator<Item = &'a u32>) -> Vec<u32> {
+    let mut values: Vec<_> = iter.copied().collect();
+    values.sort();
+    values
+}
+
+#[test]
+fn hash_set_membership() {
+    let mut set = HashSet::new_with_capacity(Some(4));
+    assert!(set.is_empty());
+    assert!(set.insert(3u32));
+    assert!(set.insert(5));
+    assert!(!set.insert(3));
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&3));
+    assert!(!set.contains(&4));
+    assert!(set.remove(&3));
+    assert!(!set.remove(&3));
+    assert!(!set.contains(&3));
+    assert_eq!(sorted(set.iter()), [5]);
+    assert_eq!(format!("{set:?}"), "{5}");
+
+    // Looking at the values needs no hashing bounds, and the length is
+    // known upfront.
+    fn describe<T: core::fmt::Debug, S>(set: &HashSet<T, S>) -> (usize, usize, String) {
+        (set.len(), set.iter().len(), format!("{set:?}"))
+    }
+    set.insert(7);
+    let (len, iter_len, _) = describe(&set);
+    assert_eq!((len, iter_len), (2, 2));
+    let mut iter = set.iter();
+    iter.next();
+    assert_eq!(iter.len(), 1);
+    assert_eq!(describe(&HashSet::<u32>::new()), (0, 0, String::from("{}")));
+}
+
+#[test]
+fn hash_set_operations() {
+    let a: HashSet<u32> = [1, 2, 3, 4].into_iter().collect();
+    let mut b = HashSet::new_with_capacity(Some(4));
+    b.extend([3, 4, 5, 6]);
+
+    assert_eq!(sorted(a.union(&b)), [1, 2, 3, 4, 5, 6]);
+    assert_eq!(sorted(a.intersection(&b)), [3, 4]);
+    assert_eq!(sorted(a.difference(&b)), [1, 2]);
+    assert_eq!(sorted(b.difference(&a)), [5, 6]);
+}
+
+#[test]
+fn merge_with_resolves_conflicts() {
+    let base = || -> HashMap<String, u32> {
+        [("a", 1), ("b", 2)]
+            .into_iter()
+            .map(|(k, v)| (String::from(k), v))
+            .collect()
+    };
+    let other = || -> HashMap<String, u32> {
+        [("b", 20), ("c", 30)]
+            .into_iter()
+            .map(|(k, v)| (String::from(k), v))
+            .collect()
+    };
+
+    let mut sum = base();
+    let mut conflicts = Vec::new();
+    sum.merge_with(other(), |k, existing, incoming| {
+        conflicts.push(k.clone());
+        existing + incoming
+    });
+    assert_eq!(conflicts, ["b"]);
+    assert_eq!(sum.len(), 3);
+    assert_eq!(sum[&String::from("a")], 1);
+    assert_eq!(sum[&String::from("b")], 22);
+    assert_eq!(sum[&String::from("c")], 30);
+
+    let mut keep = base();
+    keep.merge_with(other(), |_, existing, _| existing);
+    assert_eq!(keep[&String::from("b")], 2);
+    assert_eq!(keep[&String::from("c")], 30);
+}
+
+#[test]
+fn default_map_is_empty() {
+    let map = HashMap::<u32, u32>::default();
+    assert!(map.is_empty());
+    assert_eq!(map.capacity(), DEFAULT_CAPACITY);
+
+    #[derive(Default)]
+    struct Mounts {
+        by_path: HashMap<String, usize>,
+        count: usize,
+    }
+    let mut mounts = Mounts::default();
+    assert!(mounts.by_path.is_empty());
+    mounts.by_path.insert(String::from("/proc"), mounts.count);
+    assert_eq!(mounts.by_path.get("/proc"), Some(&0));
+}
+
+#[test]
+fn entry_or_default_groups_values() {
+    let items = [
+        ("fruit", "apple"),
+        ("veg", "leek"),
+        ("fruit", "pear"),
+        ("nut", "pecan"),
+    ];
+    let mut groups: HashMap<&str, Vec<&str>> = HashMap::new_with_capacity(Some(4));
+    for (category, item) in items {
+        groups.entry(category).or_default().push(item);
+    }
+    assert_eq!(groups.len(), 3);
+    assert_eq!(groups[&"fruit"], ["apple", "pear"]);
+    assert_eq!(groups[&"veg"], ["leek"]);
+    assert_eq!(groups[&"nut"], ["pecan"]);
+}
+
+#[test]
+fn ideal_buckets_are_uniform() {
+    // 3000 keys fit in 4096 buckets without resizing. Split the buckets in
+    // 64 ranges, each expected to receive about 47 keys.
+    const CAPACITY: usize = 4096;
+    // Both folds carry high bits down into the mask.
+    assert_eq!(super::probe::ideal_bucket(1 << 32, CAPACITY), 1);
+    assert_eq!(super::probe::ideal_bucket(1 << 48, CAPACITY), 1);
+
+    let mut map = HashMap::new_with_capacity(Some(CAPACITY));
+    let mut ranges = [0; 64];
+    for i in 0..3000 {
+        let key = format!("key_{i}");
+        ranges[ideal_bucket(&key, CAPACITY) / 64] += 1;
+        map.insert(key, i);
+    }
+    assert_eq!(map.capacity(), CAPACITY);
+    assert!(ranges.iter().all(|&n| (20..=80).contains(&n)), "{ranges:?}");
+
+    // Sequential integer keys differ only in their low bytes.
+    let mut ranges = [0; 64];
+    for i in 0..3000u32 {
+        ranges[ideal_bucket(&i, CAPACITY) / 64] += 1;
+    }
+    assert!(ranges.iter().all(|&n| (20..=80).contains(&n)), "{ranges:?}");
+}
+
+#[test]
+fn shrink_to_fit_reclaims_buckets() {
+    let mut map = HashMap::new_with_capacity(Some(16));
+    map.reserve(10_000);
+    for i in 0..10_000 {
+        map.insert(i, i * 2);
+    }
+    let capacity = map.capacity();
+    map.retain(|&k, _| k % 1000 == 0);
+    assert_eq!(map.len(), 10);
+    assert_eq!(map.capacity(), capacity);
+
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), 16);
+    for i in (0..10_000).step_by(1000) {
+        assert_eq!(map.get(&i), Some(&(i * 2)));
+    }
+    assert_eq!(map.get(&1), None);
+
+    // Never below the minimum, and never grows.
+    map.clear();
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), 8);
+    let mut map: HashMap<u32, u32> = HashMap::new_with_capacity(Some(0));
+    map.shrink_to_fit();
+    assert_eq!(map.capacity(), MIN_CAPACITY);
+}
+
+#[test]
+fn new_map_starts_small() {
+    let mut map = HashMap::new();
+    assert_eq!(map.capacity(), DEFAULT_CAPACITY);
+    assert_eq!(DEFAULT_CAPACITY, 16);
+    for i in 0..10_000u32 {
+        map.insert(i, i + 1);
+    }
+    assert!(map.capacity() >= 10_000);
+    assert_eq!(map.len(), 10_000);
+    assert!((0..10_000).all(|i| map.get(&i) == Some(&(i + 1))));
+
+    let map: HashMap<u32, u32> = (0..4).map(|i| (i, i)).collect();
+    assert_eq!(map.capacity(), DEFAULT_CAPACITY);
+}
+
+#[test]
+fn iteration_visits_only_entries() {
+    let mut map = HashMap::new_with_capacity(Some(1 << 16));
+    for i in 0..5u32 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.iter().len(), 5);
+    assert_eq!(map.iter().count(), 5);
+    assert_eq!(sorted(map.values()), [0, 1, 2, 3, 4]);
+
+    // Removing an entry moves the last one in the dense list into its place.
+    map.remove(&0);
+    map.remove(&3);
+    assert_eq!(map.iter().count(), map.len());
+    assert_eq!(sorted(map.keys()), [1, 2, 4]);
+    map.assert_entries_consistent();
+}
+
+#[test]
+fn entries_stay_consistent() {
+    let mut map = HashMap::new_with_capacity(Some(8));
+    for i in 0..200u32 {
+        map.insert(i, i);
+        if i % 3 == 0 {
+            map.remove(&(i / 2));
+        }
+    }
+    map.assert_entries_consistent();
+    map.retain(|&k, _| k % 5 != 0);
+    map.assert_entries_consistent();
+    *map.entry(7).or_insert(0) += 1;
+    map.entry(1000).or_insert(1000);
+    let other: HashMap<u32, u32> = (0..50).map(|i| (i, 1)).collect();
+    map.merge_with(other, |_, a, b| a + b);
+    map.assert_entries_consistent();
+    map.shrink_to_fit();
+    map.assert_entries_consistent();
+
+    let mut expected: Vec<u32> = map.keys().copied().collect();
+    expected.sort_unstable();
+    let mut keys: Vec<u32> = (0..1001).filter(|k| map.contains_key(k)).collect();
+    keys.sort_unstable();
+    assert_eq!(keys, expected);
+    assert_eq!(map.iter().count(), map.len());
+
+    map.drain().for_each(drop);
+    map.assert_entries_consistent();
+    assert_eq!(map.iter().count(), 0);
+}
+
+#[test]
+fn fnv_seeds_are_independent() {
+    let hash = |seed| {
+        let mut hasher = FNV1aHasher::with_seed(seed);
+        hasher.write(b"bloom");
+        hasher.finish()
+    };
+    assert_ne!(hash(1), hash(2));
+    let mut hasher = FNV1aHasher::default();
+    hasher.write(b"bloom");
+    assert_eq!(hash(FNV_OFFSET_BASIS), hasher.finish());
+
+    let mut hasher = FNV1aHasher::with_seed(1);
+    hasher.write(b"discarded");
+    hasher.reset_with(2);
+    hasher.write(b"bloom");
+    assert_eq!(hasher.finish(), hash(2));
+}
+
+#[test]
+fn bloom_filter_has_no_false_negatives() {
+    let mut filter = BloomFilter::new(1000, 0.01);
+    // About 9.6 bits and 7 hashes per item.
+    assert!((9000..10_000).contains(&filter.num_bits()));
+    assert_eq!(filter.num_hashes(), 7);
+    // About 4.8 bits and 3.3 hashes per item, rounded down.
+    assert_eq!(BloomFilter::new(1000, 0.1).num_hashes(), 3);
+
+    assert!(!filter.contains("item_0"));
+    for i in 0..1000 {
+        filter.insert(format!("item_{i}").as_str());
+    }
+    assert!((0..1000).all(|i| filter.contains(format!("item_{i}").as_str())));
+
+    filter.clear();
+    assert!(!filter.contains("item_0"));
+}
+
+#[test]
+fn bloom_filter_false_positive_rate() {
+    for (rate, hasher) in [
+        (0.01, BuildSipHasher13::new(1, 2)),
+        (0.1, BuildSipHasher13::new(3, 4)),
+    ] {
+        let mut filter = BloomFilter::with_hasher(1000, rate, hasher);
+        for i in 0..1000u32 {
+            filter.insert(&i);
+        }
+        let false_positives = (1000..101_000u32).filter(|i| filter.contains(i)).count();
+        let observed = false_positives as f64 / 100_000.0;
+        assert!(observed < rate * 1.5, "{observed} for {rate}");
+    }
+
+    // The default hasher only needs to be within the same ballpark.
+    let mut filter = BloomFilter::new(1000, 0.01);
+    for i in 0..1000 {
+        filter.insert(format!("page_{i}").as_str());
+    }
+    let false_positives = (0..10_000)
+        .filter(|i| filter.contains(format!("absent_{i}").as_str()))
+        .count();
+    assert!(false_positives < 200, "{false_positives}");
+}
+
+#[test]
+fn str_hash_matches_write_str() {
+    let mut by_hash = FNV1aHasher::default();
+    "abc".hash(&mut by_hash);
+    let mut by_write_str = FNV1aHasher::default();
+    by_write_str.write_str("abc");
+    assert_eq!(by_hash.finish(), by_write_str.finish());
+    assert_eq!(fnv_hash(&String::from("abc")), by_hash.finish());
+
+    // The terminator keeps adjacent strings apart.
+    assert_ne!(fnv_hash(&("ab", "c")), fnv_hash(&("a", "bc")));
+    assert_ne!(
+        fnv_hash(&(String::from("ab"), String::from("c"))),
+        fnv_hash(&(String::from("a"), String::from("bc"))),
+    );
+    assert_ne!(fnv_hash(&["ab", ""]), fnv_hash(&["a", "b"]));
+}
+
+#[test]
+fn crc32_matches_zlib() {
+    let crc32 = |bytes: &[u8]| {
+        let mut hasher = Crc32Hasher::default();
+        hasher.write(bytes);
+        hasher.finish()
+    };
+    assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(
+        crc32(b"The quick brown fox jumps over the lazy dog"),
+        0x414f_a339
+    );
+
+    // Streaming in pieces gives the same checksum.
+    let mut hasher = Crc32Hasher::default();
+    hasher.write(b"1234");
+    hasher.write(b"56789");
+    assert_eq!(hasher.crc32(), 0xcbf4_3926);
+
+    // Integers are hashed as little-endian bytes on every target.
+    assert_eq!(BuildCrc32.hash_one(0x3433_3231u32), crc32(b"1234"));
+    let mut map = HashMap::with_hasher(16, BuildCrc32);
+    map.insert(7u64, "seven");
+    assert_eq!(map.get(&7), Some(&"seven"));
+}
+
+#[test]
+fn robin_hood_bounds_probe_distance() {
+    // Just under the load factor, the longest probe sequence stays short.
+    let mut map = HashMap::new_with_capacity(Some(1 << 14));
+    for i in 0..12_000 {
+        map.insert(format!("key_{i}"), i);
+    }
+    assert_eq!(map.capacity(), 1 << 14);
+    assert!(
+        map.max_probe_length() <= 12,
+        "max distance {}",
+        map.max_probe_length()
+    );
+    map.assert_entries_consistent();
+
+    // Displaced entries are still found, also across removals and rehashes.
+    for i in (0..12_000).step_by(3) {
+        assert_eq!(map.remove(&format!("key_{i}")), Some(i));
+    }
+    for i in 12_000..20_000 {
+        map.insert(format!("key_{i}"), i);
+    }
+    map.assert_entries_consistent();
+    assert!(
+        map.max_probe_length() <= 12,
+        "max distance {}",
+        map.max_probe_length()
+    );
+    for i in 0..20_000 {
+        let expected = (i >= 12_000 || i % 3 != 0).then_some(&i);
+        assert_eq!(map.get(&format!("key_{i}")), expected);
+    }
+}
+
+#[test]
+fn with_capacity_and_hasher() {
+    let written = Cell::new(0);
+    let mut map = HashMap::with_capacity_and_hasher(64, BuildCounting(&written));
+    assert_eq!(map.capacity(), 64);
+    map.insert(1u32, "one");
+    map.insert(2, "two");
+    assert_eq!(written.get(), 8);
+    assert_eq!(map.get(&2), Some(&"two"));
+    assert_eq!(written.get(), 12);
+    assert_eq!(map.capacity(), 64);
+}
+
+#[test]
+fn probe_length_reports_collisions() {
+    let map: HashMap<u32, u32> = HashMap::new();
+    assert_eq!(map.max_probe_length(), 0);
+    assert_eq!(map.average_probe_length(), 0.0);
+
+    // Every key has the same ideal bucket, so the n-th one inserted sits n
+    // steps away from it.
+    let written = Cell::new(0);
+    let mut map = HashMap::with_hasher(64, BuildCounting(&written));
+    for i in 0..10u32 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.max_probe_length(), 9);
+    assert_eq!(map.average_probe_length(), 4.5);
+
+    let mut map = HashMap::new_with_capacity(Some(1024));
+    for i in 0..100 {
+        map.insert(format!("key_{i}"), i);
+    }
+    assert!(map.max_probe_length() <= 3);
+    assert!(map.average_probe_length() < 0.5);
+}
+
+#[test]
+fn collision_count_tracks_taken_ideal_buckets() {
+    // Every key has the same ideal bucket, so all inserts but the first
+    // collide.
+    let written = Cell::new(0);
+    let mut map = HashMap::with_hasher(64, BuildCounting(&written));
+    for i in 0..10u32 {
+        map.insert(i, i);
+    }
+    assert_eq!(map.collision_count(), 9);
+    // Updates don't insert, and a tombstone in the ideal bucket is reused.
+    map.insert(5, 50);
+    map.remove(&0);
+    map.insert(10, 10);
+    assert_eq!(map.collision_count(), 9);
+    map.clear();
+    assert_eq!(map.collision_count(), 0);
+
+    let mut map = HashMap::new_with_capacity(Some(64));
+    for (i, key) in colliding_keys(4, 64).into_iter().enumerate() {
+        map.insert(key, i);
+    }
+    assert_eq!(map.collision_count(), 3);
+    map.reserve(1000);
+    assert_eq!(map.collision_count(), 0);
+
+    // Merging into existing keys re-places them without new collisions,
+    // and a drain starts the count over.
+    let mut map = HashMap::with_hasher(64, BuildCounting(&written));
+    for i in 0..4u32 {
+        map.insert(i, i);
+    }
+    let mut other = HashMap::with_hasher(64, BuildCounting(&written));
+    for i in 0..4u32 {
+        other.insert(i, i);
+    }
+    map.merge_with(other, |_, a, b| a + b);
+    assert_eq!(map.collision_count(), 3);
+    assert_eq!(map[&3], 6);
+    assert_eq!(map.drain().count(), 4);
+    assert_eq!(map.collision_count(), 0);
+}
+
+#[test]
+fn borrowed_lookups() {
+    let mut map = HashMap::new();
+    map.insert(String::from("alpha"), 1);
+    map.insert(String::from("beta"), 2);
+
+    assert_eq!(map.get("alpha"), Some(&1));
+    assert_eq!(map.get_key_value("beta"), Some((&String::from("beta"), &2)));
+    assert!(map.contains_key("beta"));
+    assert!(!map.contains_key("gamma"));
+    *map.get_mut("beta").unwrap() += 10;
+    assert_eq!(map["beta"], 12);
+    assert_eq!(map.remove("alpha"), Some(1));
+    assert_eq!(map.get("alpha"), None);
+
+    let mut bytes = HashMap::new();
+    bytes.insert(vec![1u8, 2], "pair");
+    assert_eq!(bytes.get(&[1u8, 2][..]), Some(&"pair"));
+
+    let mut set = HashSet::new();
+    set.insert(String::from("page"));
+    assert!(set.contains("page"));
+    assert!(set.remove("page"));
+    assert!(!set.contains("page"));
+}
+
+#[test]
+fn update_or_insert_probes_once() {
+    let written = Cell::new(0);
+    let mut map = HashMap::with_hasher(16, BuildCounting(&written));
+    let mut calls = 0;
+    map.update_or_insert(1u32, 10, |_| calls += 1);
+    assert_eq!(calls, 0);
+    assert_eq!(map.get(&1), Some(&10));
+    assert_eq!(written.get(), 8);
+
+    map.update_or_insert(1, 0, |v| {
+        calls += 1;
+        *v += 5;
+    });
+    assert_eq!(calls, 1);
+    assert_eq!(map.get(&1), Some(&15));
+    assert_eq!(map.len(), 1);
+    // One hash for each call, plus one for each `get`.
+    assert_eq!(written.get(), 4 * 4);
+}
+
+#[test]
+fn get_or_insert_with_calls_make_once() {
+    let written = Cell::new(0);
+    let mut map = HashMap::with_hasher(16, BuildCounting(&written));
+    let mut calls = 0;
+    *map.get_or_insert_with(1u32, || {
+        calls += 1;
+        10
+    }) += 1;
+    assert_eq!(calls, 1);
+    assert_eq!(written.get(), 4);
+
+    let value = map.get_or_insert_with(1, || {
+        calls += 1;
+        0
+    });
+    assert_eq!(*value, 11);
+    *value *= 2;
+    assert_eq!(calls, 1);
+    assert_eq!(written.get(), 8);
+    assert_eq!(map.get(&1), Some(&22));
+    assert_eq!(map.len(), 1);
+}
+
+/// A string key counting how many times it is built or cloned.
+#[derive(PartialEq, Eq)]
+struct CountedKey(String);
+
+std::thread_local! {
+    static KEYS_MADE: Cell<usize> = const { Cell::new(0) };
+}
+
+impl From<&str> for CountedKey {
+    fn from(s: &str) -> Self {
+        KEYS_MADE.set(KEYS_MADE.get() + 1);
+        Self(String::from(s))
+    }
+}
+
+impl Clone for CountedKey {
+    fn clone(&self) -> Self {
+        KEYS_MADE.set(KEYS_MADE.get() + 1);
+        Self(self.0.clone())
+    }
+}
+
+impl core::borrow::Borrow<str> for CountedKey {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Hash for CountedKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+#[test]
+fn entry_ref_builds_keys_only_on_insert() {
+    let mut map: HashMap<CountedKey, u32> = HashMap::new();
+    *map.entry_ref("eth0").or_insert(0) += 1;
+    assert_eq!(KEYS_MADE.get(), 1);
+
+    for _ in 0..3 {
+        *map.entry_ref("eth0").or_insert(0) += 1;
+        map.entry_ref("eth0").and_modify(|n| *n *= 2);
+    }
+    assert_eq!(KEYS_MADE.get(), 1);
+    assert_eq!(map.get("eth0"), Some(&22));
+
+    match map.entry_ref("lo") {
+        EntryRef::Occupied(_) => panic!("lo is not in the map"),
+        EntryRef::Vacant(entry) => {
+            assert_eq!(entry.key(), "lo");
+            assert_eq!(*entry.insert(7), 7);
+        }
+    }
+    assert_eq!(KEYS_MADE.get(), 2);
+    assert_eq!(map.entry_ref("lo").key(), "lo");
+    assert_eq!(*map.entry_ref("lo").or_default(), 7);
+    assert_eq!(KEYS_MADE.get(), 2);
+    assert_eq!(map.len(), 2);
+
+    // The borrowed key only has to outlive the lookup, not the entry.
+    let count = map.entry_ref(format!("eth{}", 1).as_str()).or_insert(0);
+    *count += 5;
+    assert_eq!(map.get("eth1"), Some(&5));
+    assert_eq!(KEYS_MADE.get(), 3);
+}
+
+#[test]
+fn array_hash_map_fails_when_full() {
+    // IRQ number to the hart it is routed to.
+    let mut routes: ArrayHashMap<u32, usize, 4> = ArrayHashMap::new();
+    for (irq, hart) in [(10, 0), (11, 1), (33, 2), (34, 3)] {
+        assert_eq!(routes.insert(irq, hart).unwrap(), None);
+    }
+    assert!(routes.is_full());
+    let err = routes.insert(40, 0).unwrap_err();
+    assert_eq!((err.key, err.value), (40, 0));
+    assert_eq!(
+        format!("{err}"),
+        "failed to insert key 40 with value 0, the map is full"
+    );
+
+    // Replacing a value still works when full.
+    assert_eq!(routes.insert(33, 0).unwrap(), Some(2));
+    assert_eq!(routes.len(), 4);
+    for (irq, hart) in [(10, 0), (11, 1), (33, 0), (34, 3)] {
+        assert_eq!(routes.get(&irq), Some(&hart));
+    }
+    assert_eq!(routes.get(&40), None);
+
+    assert_eq!(routes.remove(&11), Some(1));
+    assert_eq!(routes.remove(&11), None);
+    assert!(!routes.contains_key(&11));
+    assert_eq!(routes.insert(40, 1).unwrap(), None);
+    *routes.get_mut(&10).unwrap() = 3;
+    let mut entries: Vec<_> = routes.iter().map(|(&k, &v)| (k, v)).collect();
+    entries.sort_unstable();
+    assert_eq!(entries, [(10, 3), (33, 0), (34, 3), (40, 1)]);
+    routes.clear();
+    assert!(routes.is_empty());
+    assert_eq!(routes.get(&10), None);
+}
+
+#[test]
+fn array_hash_map_of_one_entry() {
+    let mut map: ArrayHashMap<&str, u32, 1> = ArrayHashMap::new();
+    assert_eq!(map.insert("timer", 5).unwrap(), None);
+    assert!(map.is_full());
+    assert!(map.insert("uart", 10).is_err());
+    assert_eq!(map.insert("timer", 7).unwrap(), Some(5));
+    assert_eq!(map.get("timer"), Some(&7));
+    assert_eq!(map.get("uart"), None);
+    assert_eq!(map.remove("timer"), Some(7));
+    assert_eq!(map.insert("uart", 10).unwrap(), None);
+    assert_eq!(map.get("uart"), Some(&10));
+}
+
+#[test]
+fn array_hash_map_matches_hash_map() {
+    let mut array: ArrayHashMap<u32, u32, 16> = ArrayHashMap::new();
+    let mut map = HashMap::new();
+    let mut seed = 1u32;
+    for step in 0..2000 {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        let key = (seed >> 16) % 24;
+        if seed % 3 == 0 {
+            assert_eq!(array.remove(&key), map.remove(&key), "step {step}");
+        } else if map.contains_key(&key) || map.len() < 16 {
+            assert_eq!(array.insert(key, step).unwrap(), map.insert(key, step));
+        } else {
+            assert!(array.insert(key, step).is_err());
+        }
+        assert_eq!(array.len(), map.len());
+        for key in 0..24 {
+            assert_eq!(array.get(&key), map.get(&key), "step {step}");
+        }
+    }
+}
+
+#[test]
+fn multi_map_groups_values() {
+    let mut fds: MultiMap<u32, u32, String> = MultiMap::new();
+    assert!(fds.is_empty());
+    for pid in 1..=3u32 {
+        for fd in 0..pid * 10 {
+            assert_eq!(fds.insert(pid, fd, format!("{pid}:{fd}")), None);
+        }
+    }
+    assert_eq!(fds.len(), 60);
+    assert_eq!(fds.group_count(), 3);
+    assert_eq!(fds.group(&3).map(HashMap::len), Some(30));
+    assert_eq!(fds.get(&2, &19).map(String::as_str), Some("2:19"));
+    assert_eq!(fds.get(&1, &19), None);
+    assert_eq!(fds.get(&4, &0), None);
+
+    let old = fds.insert(1, 0, String::from("stdin"));
+    assert_eq!(old.as_deref(), Some("1:0"));
+    fds.get_mut(&1, &1).unwrap().push_str(" (stdout)");
+    assert_eq!(fds.len(), 60);
+
+    let mut triples: Vec<_> = fds
+        .iter()
+        .filter(|&(_, &fd, _)| fd < 2)
+        .map(|(&pid, &fd, name)| (pid, fd, name.as_str()))
+        .collect();
+    triples.sort();
+    assert_eq!(
+        triples,
+        [
+            (1, 0, "stdin"),
+            (1, 1, "1:1 (stdout)"),
+            (2, 0, "2:0"),
+            (2, 1, "2:1"),
+            (3, 0, "3:0"),
+            (3, 1, "3:1"),
+        ]
+    );
+    assert_eq!(fds.iter().count(), 60);
+
+    // Emptied groups go away.
+    for fd in 0..10 {
+        assert!(fds.remove(&1, &fd).is_some());
+    }
+    assert_eq!(fds.group_count(), 2);
+    assert_eq!(fds.remove_group(&2).map(|g| g.len()), Some(20));
+    assert_eq!(fds.len(), 30);
+    fds.clear();
+    assert!(fds.is_empty());
+    assert_eq!(fds.iter().count(), 0);
+}
+
+#[test]
+fn rw_hash_map_read_guard() {
+    let map: RwHashMap<String, u32> = RwHashMap::new();
+    assert_eq!(map.write_insert(String::from("init"), 1), None);
+    assert_eq!(map.write_insert(String::from("init"), 2), Some(1));
+    {
+        let a = map.read_get("init").unwrap();
+        let b = map.read_get("init").unwrap();
+        assert_eq!((*a, *b), (2, 2));
+        assert!(map.read_get("absent").is_none());
+        assert_eq!(map.len(), 1);
+    }
+    assert_eq!(map.write_remove("init"), Some(2));
+    assert!(map.is_empty());
+    assert_eq!(format!("{map:?}"), "{}");
+}
+
+#[test]
+fn rw_hash_map_default() {
+    #[derive(Default)]
+    struct Registry {
+        drivers: RwHashMap<&'static str, u32>,
+        irqs: RwHashMap<u32, u32, SpinRwLock, BuildFxHasher>,
+    }
+    let registry = Registry::default();
+    assert!(registry.drivers.is_empty());
+    registry.irqs.write_insert(10, 1);
+    assert_eq!(*registry.irqs.read_get(&10).unwrap(), 1);
+    assert_eq!(registry.irqs.read(|m| m.capacity()), DEFAULT_CAPACITY);
+}
+
+#[test]
+fn spin_rw_lock_lets_a_waiting_writer_go_first() {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    let lock = Arc::new(SpinRwLock::INIT);
+    let order = Arc::new(Mutex::new(Vec::new()));
+    lock.lock_shared();
+
+    let writer = {
+        let (lock, order) = (lock.clone(), order.clone());
+        thread::spawn(move || {
+            lock.lock_exclusive();
+            order.lock().unwrap().push("writer");
+            unsafe { lock.unlock_exclusive() };
+        })
+    };
+    thread::sleep(Duration::from_millis(100));
+    // The writer now waits for the first reader, and holds off new ones.
+    let reader = {
+        let (lock, order) = (lock.clone(), order.clone());
+        thread::spawn(move || {
+            lock.lock_shared();
+            order.lock().unwrap().push("reader");
+            unsafe { lock.unlock_shared() };
+        })
+    };
+    thread::sleep(Duration::from_millis(100));
+    assert!(order.lock().unwrap().is_empty());
+
+    unsafe { lock.unlock_shared() };
+    writer.join().unwrap();
+    reader.join().unwrap();
+    assert_eq!(*order.lock().unwrap(), ["writer", "reader"]);
+}
+
+#[test]
+fn rw_hash_map_concurrent_readers() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    // Every write keeps the invariant `map[i] == map[0] + i`, which readers
+    // check under a single shared lock.
+    let map: Arc<RwHashMap<u32, u32>> = Arc::new(RwHashMap::new());
+    map.write(|m| m.extend((0..16).map(|i| (i, i))));
+    let done = Arc::new(AtomicBool::new(false));
+
+    // Readers take the lock back to back, with no pause in between, yet the
+    // writer gets through.
+    let readers: Vec<_> = (0..4)
+        .map(|_| {
+            let (map, done) = (map.clone(), done.clone());
+            thread::spawn(move || {
+                let mut reads = 0;
+                while !done.load(Ordering::Relaxed) || reads == 0 {
+                    map.read(|m| {
+                        let base = m[&0];
+                        assert!((0..16).all(|i| m[&i] == base + i));
+                    });
+                    assert!(*map.read_get(&15).unwrap() >= 15);
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect();
+
+    for round in 1..=1000 {
+        map.write(|m| {
+            for i in 0..16 {
+                m.insert(i, round + i);
+            }
+        });
+    }
+    done.store(true, Ordering::Relaxed);
+    for reader in readers {
+        assert!(reader.join().unwrap() > 0);
+    }
+    let map = Arc::try_unwrap(map).ok().unwrap().into_inner();
+    assert_eq!(map[&15], 1015);
+}